@@ -0,0 +1,154 @@
+use crate::chessboard::Coordinate;
+use crate::pieces::{Color, KNIGHT_OFFSETS};
+
+// a bitboard is a u64 where bit x + 8 * y tells if something is on the square (x, y), the same layout
+// Chessboard::piece_bitboards uses. Squares are passed around as that bit index.
+
+pub fn square_index(coordinate: Coordinate) -> u8 {
+    coordinate.x + 8 * coordinate.y
+}
+pub fn coordinate(square: u8) -> Coordinate {
+    Coordinate::new(square % 8, square / 8)
+}
+pub fn from_coordinate(coordinate: Coordinate) -> u64 {
+    1 << square_index(coordinate)
+}
+/** the coordinates of all the set bits, from a1 to h8 */
+pub fn coordinates(bitboard: u64) -> impl Iterator<Item = Coordinate> {
+    squares(bitboard).map(coordinate)
+}
+/** the indices of all the set bits, from a1 to h8 */
+pub fn squares(mut bitboard: u64) -> impl Iterator<Item = u8> {
+    std::iter::from_fn(move || {
+        if bitboard == 0 { return None; }
+        let square = bitboard.trailing_zeros() as u8;
+        bitboard &= bitboard - 1;
+        Some(square)
+    })
+}
+
+// everything below is computed at compile time, so the lookups are as cheap as they get
+
+/** the bitboard of the square at the offset, or 0 if it falls outside of the board */
+const fn offset(square: usize, dx: i8, dy: i8) -> u64 {
+    let (x, y) = ((square % 8) as i8 + dx, (square / 8) as i8 + dy);
+    if x >= 0 && x < 8 && y >= 0 && y < 8 { 1 << (x + 8 * y) } else { 0 }
+}
+
+const fn offsets_table(offsets: &[(i8, i8)]) -> [u64; 64] {
+    let mut table = [0; 64];
+    let mut square = 0;
+    while square < 64 {
+        let mut i = 0;
+        while i < offsets.len() {
+            table[square] |= offset(square, offsets[i].0, offsets[i].1);
+            i += 1;
+        }
+        square += 1;
+    }
+    table
+}
+
+/** N, E, NE, NW walk towards higher bit indices, S, W, SW, SE towards lower ones */
+const DIRECTIONS: [(i8, i8); 8] = [(0, 1), (1, 0), (1, 1), (-1, 1), (0, -1), (-1, 0), (-1, -1), (1, -1)];
+const ROOK_DIRECTIONS: [usize; 4] = [0, 1, 4, 5];
+const BISHOP_DIRECTIONS: [usize; 4] = [2, 3, 6, 7];
+
+/** the squares reached walking in each direction from each square until the edge of the board */
+const fn rays_table() -> [[u64; 64]; 8] {
+    let mut table = [[0; 64]; 8];
+    let mut direction = 0;
+    while direction < 8 {
+        let mut square = 0;
+        while square < 64 {
+            let (dx, dy) = DIRECTIONS[direction];
+            let mut distance = 1;
+            while distance < 8 {
+                table[direction][square] |= offset(square, dx * distance, dy * distance);
+                distance += 1;
+            }
+            square += 1;
+        }
+        direction += 1;
+    }
+    table
+}
+
+const fn between_table() -> [[u64; 64]; 64] {
+    let mut table = [[0; 64]; 64];
+    let mut from = 0;
+    while from < 64 {
+        let mut direction = 0;
+        while direction < 8 {
+            let mut ray = RAYS[direction][from];
+            while ray != 0 {
+                let to = ray.trailing_zeros() as usize;
+                table[from][to] = RAYS[direction][from] & !RAYS[direction][to] & !(1 << to);
+                ray &= ray - 1;
+            }
+            direction += 1;
+        }
+        from += 1;
+    }
+    table
+}
+
+static KNIGHT_ATTACKS: [u64; 64] = offsets_table(&KNIGHT_OFFSETS);
+static KING_ATTACKS: [u64; 64] = offsets_table(&[(-1, -1), (-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0), (1, 1)]);
+static PAWN_ATTACKS: [[u64; 64]; 2] = [offsets_table(&[(-1, 1), (1, 1)]), offsets_table(&[(-1, -1), (1, -1)])];
+static RAYS: [[u64; 64]; 8] = rays_table();
+static BETWEEN: [[u64; 64]; 64] = between_table();
+
+pub fn knight_attacks(square: u8) -> u64 {
+    KNIGHT_ATTACKS[square as usize]
+}
+
+pub fn king_attacks(square: u8) -> u64 {
+    KING_ATTACKS[square as usize]
+}
+
+/** the squares a pawn of the color standing on the square captures on */
+pub fn pawn_attacks(square: u8, color: Color) -> u64 {
+    PAWN_ATTACKS[color as usize][square as usize]
+}
+
+/** the ray in the direction, cut right after the first occupied square */
+fn sliding_attacks(square: u8, direction: usize, occupancy: u64) -> u64 {
+    let ray = RAYS[direction][square as usize];
+    let blockers = ray & occupancy;
+    if blockers == 0 { return ray; }
+    // the first blocker is the closest one to the square, which depends on the way the direction walks
+    let first_blocker = if direction < 4 { blockers.trailing_zeros() } else { 63 - blockers.leading_zeros() };
+    ray ^ RAYS[direction][first_blocker as usize]
+}
+
+pub fn rook_attacks(square: u8, occupancy: u64) -> u64 {
+    ROOK_DIRECTIONS.iter().fold(0, |attacks, &direction| attacks | sliding_attacks(square, direction, occupancy))
+}
+
+pub fn bishop_attacks(square: u8, occupancy: u64) -> u64 {
+    BISHOP_DIRECTIONS.iter().fold(0, |attacks, &direction| attacks | sliding_attacks(square, direction, occupancy))
+}
+
+/** the squares strictly between the two squares if they share a rank, file or diagonal, otherwise 0 */
+pub fn between(from: u8, to: u8) -> u64 {
+    BETWEEN[from as usize][to as usize]
+}
+
+#[test]
+fn attacks() {
+    let index = |name: &str| square_index(Coordinate::from_string(name).unwrap());
+    let bitboard = |names: &[&str]| names.iter().fold(0, |bitboard, name| bitboard | 1 << index(name));
+
+    assert_eq!(knight_attacks(index("a1")), bitboard(&["b3", "c2"]));
+    assert_eq!(king_attacks(index("h8")), bitboard(&["g8", "g7", "h7"]));
+    assert_eq!(pawn_attacks(index("a2"), Color::White), bitboard(&["b3"]));
+    assert_eq!(pawn_attacks(index("e5"), Color::Black), bitboard(&["d4", "f4"]));
+    assert_eq!(rook_attacks(index("d4"), bitboard(&["d6", "b4", "d1", "h8"])), bitboard(&["d5", "d6", "c4", "b4", "e4", "f4", "g4", "h4", "d3", "d2", "d1"]));
+    assert_eq!(bishop_attacks(index("a1"), bitboard(&["c3"])), bitboard(&["b2", "c3"]));
+    assert_eq!(bishop_attacks(index("e4"), bitboard(&["c2", "g6", "b7", "h1"])), bitboard(&["d3", "c2", "f5", "g6", "d5", "c6", "b7", "f3", "g2", "h1"]));
+    assert_eq!(between(index("a1"), index("d4")), bitboard(&["b2", "c3"]));
+    assert_eq!(between(index("h3"), index("c3")), bitboard(&["d3", "e3", "f3", "g3"]));
+    assert_eq!(between(index("a1"), index("b3")), 0);
+    assert_eq!(coordinates(bitboard(&["a1", "c3", "h8"])).collect::<Vec<_>>(), vec![Coordinate::new(0, 0), Coordinate::new(2, 2), Coordinate::new(7, 7)]);
+}
@@ -0,0 +1,352 @@
+use std::fmt::{Display, Formatter, Error};
+use std::path::Path;
+
+use crate::chessboard::{Chessboard, Move, SpecialMove};
+use crate::pieces::{Color, PieceType};
+
+mod builder;
+mod random64;
+
+pub use builder::{AddedGames, BookBuilder, BuildOptions};
+
+// Polyglot opening books (http://hgm.nubati.net/book_format.html) are files of 16 byte entries sorted by the key of
+// the position they are about, each one with a move and a weight telling how often it should be played. The key is
+// a Zobrist hash made with the 781 "Random64" numbers listed in the format description, which Random64::polyglot
+// gives. Books made and read with any other numbers work just as well, they just can't be shared with other programs
+
+/** how many random numbers the Polyglot key needs: 12 pieces on 64 squares, 4 castling rights, 8 files and the turn */
+pub const RANDOM_COUNT: usize = 781;
+const CASTLING_OFFSET: usize = 768;
+const EN_PASSANT_OFFSET: usize = 772;
+const TURN_OFFSET: usize = 780;
+
+/** the size of an entry in the file */
+const ENTRY_SIZE: usize = 16;
+
+/** why a book couldn't be read or saved */
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum BookError {
+    Io(std::io::ErrorKind),
+    /** the length of the book in bytes, which isn't a whole number of entries */
+    TruncatedEntry(usize),
+    /** how many random numbers were found instead of 781 */
+    WrongRandomCount(usize),
+    InvalidRandom(String)
+}
+
+impl Display for BookError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match self {
+            BookError::Io(kind) => write!(f, "couldn't access the book file: {kind}"),
+            BookError::TruncatedEntry(length) => write!(f, "a book of {length} bytes doesn't hold a whole number of {ENTRY_SIZE} byte entries"),
+            BookError::WrongRandomCount(count) => write!(f, "found {count} random numbers instead of {RANDOM_COUNT}"),
+            BookError::InvalidRandom(number) => write!(f, "'{number}' isn't a 64 bit hexadecimal number")
+        }
+    }
+}
+
+impl std::error::Error for BookError {}
+
+/** the numbers the Polyglot key of a position is made of */
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Random64(Box<[u64; RANDOM_COUNT]>);
+
+impl Default for Random64 {
+    fn default() -> Self {
+        Self::polyglot()
+    }
+}
+
+impl Random64 {
+    pub fn new(randoms: [u64; RANDOM_COUNT]) -> Self {
+        Random64(Box::new(randoms))
+    }
+
+    /** the numbers of the format description, the ones every Polyglot book out there is made with */
+    pub fn polyglot() -> Self {
+        Self::new(random64::POLYGLOT_RANDOM64)
+    }
+
+    /**
+        reads the numbers from text listing them in hexadecimal, like the C array of the format description
+        (0x9D39247E33776D41, ...). Anything that doesn't start with 0x is skipped, and so are the U and L suffixes
+    */
+    pub fn parse(text: &str) -> Result<Self, BookError> {
+        let randoms = text.split(|character: char| !character.is_ascii_alphanumeric())
+            .filter_map(|word| word.strip_prefix("0x").or_else(|| word.strip_prefix("0X")))
+            .map(|digits| {
+                let digits = digits.trim_end_matches(['u', 'U', 'l', 'L']);
+                u64::from_str_radix(digits, 16).map_err(|_| BookError::InvalidRandom(format!("0x{digits}")))
+            })
+            .collect::<Result<Vec<u64>, BookError>>()?;
+        let count = randoms.len();
+        randoms.try_into().map(Random64).map_err(|_| BookError::WrongRandomCount(count))
+    }
+
+    /**
+        the Polyglot key of the position: the pieces, the castling rights, the file of the en passant target if
+        a pawn of the player to play stands next to the pawn that can be captured, and white to play
+    */
+    pub fn key(&self, chessboard: &Chessboard) -> u64 {
+        let mut key = 0;
+        for (x, y) in (0..8).flat_map(|y| (0..8).map(move |x| (x, y))) {
+            let Ok(Some(piece)) = chessboard.get(x, y) else { continue };
+            // the kinds go black pawn, white pawn, black knight, white knight... up to white king
+            let kind = 2 * match piece.piece_type {
+                PieceType::Pawn => 0,
+                PieceType::Knight => 1,
+                PieceType::Bishop => 2,
+                PieceType::Rook => 3,
+                PieceType::Queen => 4,
+                PieceType::King => 5
+            } + (piece.color == Color::White) as usize;
+            key ^= self.0[64 * kind + 8 * y as usize + x as usize];
+        }
+        // Chessboard::castling is in the same order as the Polyglot keys
+        for (index, _) in chessboard.castling.iter().enumerate().filter(|(_, can_castle)| **can_castle) {
+            key ^= self.0[CASTLING_OFFSET + index];
+        }
+        if let Some(file) = chessboard.en_passant_file() { key ^= self.0[EN_PASSANT_OFFSET + file as usize]; }
+        if chessboard.to_play == Color::White { key ^= self.0[TURN_OFFSET]; }
+        key
+    }
+}
+
+/**
+    a move the way Polyglot writes it: the to file and row, then the from file and row, 3 bits each, then the
+    promotion (1 knight up to 4 queen). Castling is written as the king taking its own rook
+*/
+pub fn encode_move(r#move: Move) -> u16 {
+    let to_x = match r#move.special_move {
+        Some(SpecialMove::Castling) => if r#move.to.x > r#move.from.x { 7 } else { 0 },
+        _ => r#move.to.x
+    };
+    let promotion = match r#move.special_move {
+        Some(SpecialMove::Promotion(PieceType::Knight)) => 1,
+        Some(SpecialMove::Promotion(PieceType::Bishop)) => 2,
+        Some(SpecialMove::Promotion(PieceType::Rook)) => 3,
+        Some(SpecialMove::Promotion(PieceType::Queen)) => 4,
+        _ => 0
+    };
+    to_x as u16 | (r#move.to.y as u16) << 3 | (r#move.from.x as u16) << 6 | (r#move.from.y as u16) << 9 | promotion << 12
+}
+
+/** the legal move of the position written that way, None if there isn't one */
+pub fn decode_move(chessboard: &Chessboard, encoded: u16) -> Option<Move> {
+    chessboard.get_legal_moves().into_iter().find(|&r#move| encode_move(r#move) == encoded)
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Entry {
+    pub key: u64,
+    /** see encode_move */
+    pub r#move: u16,
+    pub weight: u16,
+    /** not used by this crate, some programs keep learning data there */
+    pub learn: u32
+}
+
+impl Entry {
+    /** entries are stored big endian */
+    pub fn from_bytes(bytes: [u8; ENTRY_SIZE]) -> Self {
+        let [key @ .., m0, m1, w0, w1, l0, l1, l2, l3] = bytes;
+        Entry {
+            key: u64::from_be_bytes(key),
+            r#move: u16::from_be_bytes([m0, m1]),
+            weight: u16::from_be_bytes([w0, w1]),
+            learn: u32::from_be_bytes([l0, l1, l2, l3])
+        }
+    }
+    pub fn to_bytes(&self) -> [u8; ENTRY_SIZE] {
+        let mut bytes = [0; ENTRY_SIZE];
+        bytes[0..8].copy_from_slice(&self.key.to_be_bytes());
+        bytes[8..10].copy_from_slice(&self.r#move.to_be_bytes());
+        bytes[10..12].copy_from_slice(&self.weight.to_be_bytes());
+        bytes[12..16].copy_from_slice(&self.learn.to_be_bytes());
+        bytes
+    }
+}
+
+/** a move of the book for a position, with how often it should be played relative to the others */
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct BookMove {
+    pub r#move: Move,
+    pub weight: u16
+}
+
+pub struct Book {
+    /** sorted by key, so the entries of a position can be found by binary search */
+    entries: Vec<Entry>,
+    randoms: Random64
+}
+
+impl Book {
+    pub fn new(mut entries: Vec<Entry>, randoms: Random64) -> Self {
+        // the format wants them sorted already, but sorting again costs little when they are
+        entries.sort_by_key(|entry| entry.key);
+        Book { entries, randoms }
+    }
+
+    /** reads a book from the contents of a .bin file */
+    pub fn from_bytes(bytes: &[u8], randoms: Random64) -> Result<Self, BookError> {
+        if !bytes.len().is_multiple_of(ENTRY_SIZE) { return Err(BookError::TruncatedEntry(bytes.len())); }
+        let entries = bytes.chunks_exact(ENTRY_SIZE)
+            .map(|chunk| Entry::from_bytes(chunk.try_into().expect("chunks are exactly one entry long")))
+            .collect();
+        Ok(Self::new(entries, randoms))
+    }
+    pub fn open(path: impl AsRef<Path>, randoms: Random64) -> Result<Self, BookError> {
+        let bytes = std::fs::read(path).map_err(|error| BookError::Io(error.kind()))?;
+        Self::from_bytes(&bytes, randoms)
+    }
+
+    /** the contents of the .bin file */
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.entries.iter().flat_map(Entry::to_bytes).collect()
+    }
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), BookError> {
+        std::fs::write(path, self.to_bytes()).map_err(|error| BookError::Io(error.kind()))
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+    pub fn randoms(&self) -> &Random64 {
+        &self.randoms
+    }
+
+    /** the moves the book has for the position, the most played first. Entries with illegal moves are left out */
+    pub fn moves(&self, chessboard: &Chessboard) -> Vec<BookMove> {
+        let key = self.randoms.key(chessboard);
+        let start = self.entries.partition_point(|entry| entry.key < key);
+        let mut moves: Vec<BookMove> = self.entries[start..].iter()
+            .take_while(|entry| entry.key == key)
+            .filter_map(|entry| Some(BookMove { r#move: decode_move(chessboard, entry.r#move)?, weight: entry.weight }))
+            .collect();
+        moves.sort_by_key(|book_move| std::cmp::Reverse(book_move.weight));
+        moves
+    }
+
+    /** the move of the book with the biggest weight */
+    pub fn best_move(&self, chessboard: &Chessboard) -> Option<Move> {
+        self.moves(chessboard).first().map(|book_move| book_move.r#move)
+    }
+
+    /**
+        picks a move with a chance proportional to its weight, using the random number given, so the caller
+        decides where randomness comes from. Moves of weight 0 are never played
+    */
+    pub fn weighted_move(&self, chessboard: &Chessboard, random: u64) -> Option<Move> {
+        let moves = self.moves(chessboard);
+        let total: u64 = moves.iter().map(|book_move| book_move.weight as u64).sum();
+        if total == 0 { return None; }
+
+        let mut pick = random % total;
+        moves.into_iter().find(|book_move| {
+            let found = pick < book_move.weight as u64;
+            pick = pick.saturating_sub(book_move.weight as u64);
+            found
+        }).map(|book_move| book_move.r#move)
+    }
+}
+
+/** any numbers work for books made and read with them, these make sure nothing depends on the standard ones */
+#[cfg(test)]
+pub(crate) fn test_randoms() -> Random64 {
+    let mut state: u64 = 0x5EED;
+    Random64::new(std::array::from_fn(|_| {
+        // splitmix64
+        state = state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }))
+}
+
+#[test]
+fn polyglot_keys() {
+    let randoms = Random64::polyglot();
+    let key = |fen: &str| randoms.key(&Chessboard::from_fen(fen).expect("FEN is invalid"));
+
+    let text: Vec<String> = randoms.0.iter().map(|random| format!("0x{random:016X}ULL")).collect();
+    let text = format!("const uint64 Random64[781] = {{\n    {}\n}};", text.join(",\n    "));
+    assert_eq!(Random64::parse(&text), Ok(randoms.clone()));
+    assert_eq!(Random64::default(), randoms);
+    assert_eq!(Random64::parse("0x1, 0x2"), Err(BookError::WrongRandomCount(2)));
+    assert_eq!(Random64::parse("0x12G4"), Err(BookError::InvalidRandom("0x12G4".to_owned())));
+
+    // the white king on e1 is kind 11, on row 0 and file 4
+    assert_eq!(key("8/8/8/8/8/8/8/4K3 b - - 0 1"), randoms.0[64 * 11 + 4]);
+    assert_eq!(key("8/8/8/8/8/8/8/4K3 w - - 0 1"), randoms.0[64 * 11 + 4] ^ randoms.0[780]);
+    assert_eq!(key("4k3/8/8/8/8/8/8/R3K3 b Q - 0 1"), randoms.0[64 * 10 + 60] ^ randoms.0[64 * 7] ^ randoms.0[64 * 11 + 4] ^ randoms.0[769]);
+
+    // the en passant file only counts if the pawn can be taken
+    let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+    let mut chessboard = Chessboard::from_fen(start).unwrap();
+    for uci in ["e2e4", "d7d5", "e4e5", "f7f5"] {
+        let r#move = chessboard.parse_uci_move(uci).unwrap();
+        chessboard.make_move(r#move);
+    }
+    assert_eq!(randoms.key(&chessboard), key("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3"));
+    assert_eq!(randoms.key(&chessboard) ^ key("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq - 0 3"), randoms.0[772 + 5]);
+    assert_eq!(key("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"), key("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1"));
+
+    // the keys the format description gives for the positions after each of these moves
+    for (moves, expected) in [
+        ("", 0x463b96181691fc9c),
+        ("e2e4", 0x823c9b50fd114196),
+        ("e2e4 d7d5", 0x0756b94461c50fb0),
+        ("e2e4 d7d5 e4e5", 0x662fafb965db29d4),
+        ("e2e4 d7d5 e4e5 f7f5", 0x22a48b5a8e47ff78),
+        ("e2e4 d7d5 e4e5 f7f5 e1e2", 0x652a607ca3f242c1),
+        ("e2e4 d7d5 e4e5 f7f5 e1e2 e8f7", 0x00fdd303c946bdd9),
+        ("a2a4 b7b5 h2h4 b5b4 c2c4", 0x3c8123ea7b067637),
+        ("a2a4 b7b5 h2h4 b5b4 c2c4 b4c3 a1a3", 0x5c3f9b829b279560)
+    ] {
+        let mut chessboard = Chessboard::from_fen(start).unwrap();
+        for uci in moves.split_whitespace() {
+            let r#move = chessboard.parse_uci_move(uci).unwrap();
+            chessboard.make_move(r#move);
+        }
+        assert_eq!(randoms.key(&chessboard), expected, "{moves}");
+    }
+}
+
+#[test]
+fn read_book() {
+    let r#move = |uci: &str, special_move| Move::from_string(uci, special_move).unwrap();
+    let castling = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+    assert_eq!(encode_move(r#move("e1g1", Some(SpecialMove::Castling))), encode_move(r#move("e1h1", None)));
+    assert_eq!(encode_move(r#move("e8c8", Some(SpecialMove::Castling))), encode_move(r#move("e8a8", None)));
+    assert_eq!(encode_move(r#move("a7a8q", None)), 0b100_110_000_111_000);
+    assert_eq!(decode_move(&Chessboard::from_fen(castling).unwrap(), encode_move(r#move("e1a1", None))), Some(r#move("e1c1", Some(SpecialMove::Castling))));
+
+    let randoms = test_randoms();
+    let start = Chessboard::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+    let entry = |chessboard: &Chessboard, uci: &str, weight| Entry { key: randoms.key(chessboard), r#move: encode_move(r#move(uci, None)), weight, learn: 0 };
+    let entries = [
+        entry(&start, "d2d4", 1),
+        entry(&Chessboard::from_fen(castling).unwrap(), "e1h1", 5),
+        entry(&start, "e2e4", 3),
+        // an illegal move, as if another position had the same key
+        entry(&start, "e2e5", 10),
+        entry(&start, "h2h3", 0)
+    ];
+    let bytes: Vec<u8> = entries.iter().flat_map(Entry::to_bytes).collect();
+    assert_eq!(Entry::from_bytes(entries[1].to_bytes()), entries[1]);
+    assert_eq!(Book::from_bytes(&bytes[1..], randoms.clone()).err(), Some(BookError::TruncatedEntry(bytes.len() - 1)));
+
+    let book = Book::from_bytes(&bytes, randoms).unwrap();
+    assert!(book.entries().is_sorted_by_key(|entry| entry.key));
+    let e4 = start.parse_uci_move("e2e4").unwrap();
+    let d4 = start.parse_uci_move("d2d4").unwrap();
+    assert_eq!(book.moves(&start), [
+        BookMove { r#move: e4, weight: 3 }, BookMove { r#move: d4, weight: 1 }, BookMove { r#move: r#move("h2h3", None), weight: 0 }
+    ]);
+    assert_eq!(book.best_move(&start), Some(e4));
+    let picks: Vec<Option<Move>> = (0..5).map(|random| book.weighted_move(&start, random)).collect();
+    assert_eq!(picks, [Some(e4), Some(e4), Some(e4), Some(d4), Some(e4)]);
+    assert_eq!(book.best_move(&Chessboard::from_fen(castling).unwrap()), Some(r#move("e1g1", Some(SpecialMove::Castling))));
+    assert_eq!(book.best_move(&Chessboard::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap()), None);
+}
@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+
+use crate::chessboard::FenError;
+use crate::pgn::{self, Game, GameResult};
+use crate::pieces::Color;
+
+use super::{encode_move, Book, Entry, Random64};
+
+/** what goes in the book */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BuildOptions {
+    /** only the first plies of every game are looked at, the book is for the opening */
+    pub max_ply: usize,
+    /** moves played fewer times than that in a position are left out, they may just be mistakes */
+    pub min_occurrences: u32
+}
+
+impl Default for BuildOptions {
+    fn default() -> Self {
+        BuildOptions { max_ply: 20, min_occurrences: 1 }
+    }
+}
+
+/** what became of the games of a PGN text */
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct AddedGames {
+    pub added: usize,
+    /** games without a result, which can't tell how good their moves were */
+    pub unfinished: usize,
+    /** games that couldn't be read, like ones with an illegal move */
+    pub invalid: usize
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Statistics {
+    occurrences: u32,
+    /** 2 for every win of the player who played the move, 1 for every draw */
+    points: u64
+}
+
+/**
+    makes a book out of games, the way Polyglot's make-book does: every move gets 2 points when the player who
+    played it won and 1 when the game was drawn, so a move's weight is how well it did along with how often
+*/
+pub struct BookBuilder {
+    randoms: Random64,
+    options: BuildOptions,
+    /** indexed by the key of the position and the encoded move */
+    statistics: HashMap<(u64, u16), Statistics>
+}
+
+impl BookBuilder {
+    pub fn new(randoms: Random64, options: BuildOptions) -> Self {
+        BookBuilder { randoms, options, statistics: HashMap::new() }
+    }
+
+    /**
+        replays the main line of the game, counting every move up to the max ply. Games without a result are
+        left out, returning false
+    */
+    pub fn add_game(&mut self, game: &Game) -> Result<bool, FenError> {
+        if game.result == GameResult::Unknown { return Ok(false); }
+        let mut chessboard = game.starting_position()?;
+        for node in game.mainline.moves.iter().take(self.options.max_ply) {
+            let points = match (game.result, chessboard.to_play) {
+                (GameResult::WhiteWins, Color::White) | (GameResult::BlackWins, Color::Black) => 2,
+                (GameResult::WhiteWins, Color::Black) | (GameResult::BlackWins, Color::White) => 0,
+                _ => 1
+            };
+            let statistics = self.statistics.entry((self.randoms.key(&chessboard), encode_move(node.r#move))).or_default();
+            statistics.occurrences += 1;
+            statistics.points += points;
+            chessboard.make_move(node.r#move);
+        }
+        Ok(true)
+    }
+
+    /** adds every game of the PGN text that can be read and has a result, skipping the others */
+    pub fn add_pgn(&mut self, pgn: &str) -> AddedGames {
+        let mut added_games = AddedGames::default();
+        for game in pgn::parse_each(pgn) {
+            let Ok(game) = game else {
+                added_games.invalid += 1;
+                continue;
+            };
+            match self.add_game(&game) {
+                Ok(true) => added_games.added += 1,
+                Ok(false) => added_games.unfinished += 1,
+                Err(_) => unreachable!("the PGN reader only accepts games with a valid FEN tag")
+            }
+        }
+        added_games
+    }
+
+    /**
+        the book of the moves played often enough that scored at least once, sorted by key and then from the
+        heaviest to the lightest. The weights of a position are scaled down if some don't fit in 16 bits
+    */
+    pub fn build(&self) -> Book {
+        let mut positions: HashMap<u64, Vec<(u16, u64)>> = HashMap::new();
+        for (&(key, r#move), statistics) in &self.statistics {
+            if statistics.occurrences < self.options.min_occurrences || statistics.points == 0 { continue; }
+            positions.entry(key).or_default().push((r#move, statistics.points));
+        }
+
+        let mut entries = Vec::with_capacity(self.statistics.len());
+        for (key, mut moves) in positions {
+            // the encoded move breaks ties, so the same games always make the same file
+            moves.sort_by_key(|&(r#move, points)| (std::cmp::Reverse(points), r#move));
+            let heaviest = moves[0].1;
+            for (r#move, points) in moves {
+                let weight = if heaviest > u16::MAX as u64 { (points * u16::MAX as u64 / heaviest).max(1) } else { points };
+                entries.push(Entry { key, r#move, weight: weight as u16, learn: 0 });
+            }
+        }
+        entries.sort_by_key(|entry| (entry.key, std::cmp::Reverse(entry.weight), entry.r#move));
+        Book::new(entries, self.randoms.clone())
+    }
+}
+
+#[test]
+fn build_book() {
+    use crate::chessboard::Chessboard;
+    use super::{test_randoms, BookMove};
+
+    let pgn = "
+        1. e4 e5 2. Nf3 Nc6 1-0
+        1. e4 c5 2. Nf3 d6 0-1
+        1. d4 d5 2. c4 1/2-1/2
+        1. e4 e5 2. Bc4 *
+        [FEN \"4k3/8/8/8/8/8/8/4K2R w K - 0 1\"]
+        [SetUp \"1\"]
+        1. O-O Kd7 1-0
+    ";
+    let mut builder = BookBuilder::new(test_randoms(), BuildOptions { max_ply: 3, min_occurrences: 1 });
+    assert_eq!(builder.add_pgn(pgn), AddedGames { added: 4, unfinished: 1, invalid: 0 });
+    // the game with an illegal move is skipped, but the one after it isn't
+    assert_eq!(builder.add_pgn("1. e5 0-1 1. d4 Nf6 0-1"), AddedGames { added: 1, unfinished: 0, invalid: 1 });
+
+    let book = builder.build();
+    let start = Chessboard::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+    let uci = |chessboard: &Chessboard, moves: &[&str]| {
+        let mut chessboard = chessboard.clone();
+        for r#move in moves { chessboard.make_move(chessboard.parse_uci_move(r#move).unwrap()); }
+        chessboard
+    };
+    let book_move = |chessboard: &Chessboard, r#move: &str, weight| BookMove { r#move: chessboard.parse_uci_move(r#move).unwrap(), weight };
+    // e4 won once and lost once, d4 was drawn once and lost once, the game without a result doesn't count
+    assert_eq!(book.moves(&start), [book_move(&start, "e2e4", 2), book_move(&start, "d2d4", 1)]);
+    // c5 won for black, and e5 lost, so it isn't in the book
+    let after_e4 = uci(&start, &["e2e4"]);
+    assert_eq!(book.moves(&after_e4), [book_move(&after_e4, "c7c5", 2)]);
+    let after_d4 = uci(&start, &["d2d4"]);
+    assert_eq!(book.moves(&after_d4), [book_move(&after_d4, "g8f6", 2), book_move(&after_d4, "d7d5", 1)]);
+    // the third ply is the last one
+    let after_e5 = uci(&start, &["e2e4", "e7e5"]);
+    assert_eq!(book.moves(&after_e5), [book_move(&after_e5, "g1f3", 2)]);
+    assert_eq!(book.moves(&uci(&start, &["e2e4", "e7e5", "g1f3"])), []);
+    let castling = Chessboard::from_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+    assert_eq!(book.best_move(&castling), castling.parse_uci_move("e1g1").ok());
+
+    // the file is sorted, and reads back the same
+    let bytes = book.to_bytes();
+    assert!(book.entries().is_sorted_by_key(|entry| entry.key));
+    assert_eq!(Book::from_bytes(&bytes, test_randoms()).unwrap().entries(), book.entries());
+
+    // moves played once are left out
+    let mut builder = BookBuilder::new(test_randoms(), BuildOptions { max_ply: 1, min_occurrences: 2 });
+    builder.add_pgn(pgn);
+    let book = builder.build();
+    assert_eq!(book.moves(&start), [book_move(&start, "e2e4", 2)]);
+    assert_eq!(book.entries().len(), 1);
+}
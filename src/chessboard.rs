@@ -1,6 +1,18 @@
-use crate::pieces::{Piece, Color, PieceType};
+use crate::{bitboard, zobrist};
+use crate::pieces::{Piece, Color, PieceType, PROMOTION_PIECES};
 use std::fmt::{Display, Formatter, Error};
 
+mod fen;
+mod movegen;
+mod perft;
+mod san;
+mod see;
+mod uci;
+
+pub use fen::{FenError, STARTING_FEN};
+pub use san::SanError;
+pub use uci::UciMoveError;
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub struct Coordinate {
     pub x: u8,
@@ -12,19 +24,14 @@ impl Coordinate {
         Self { x, y }
     }
     pub fn from_string(string: &str) -> Option<Self> {
-        let mut chars = string.chars();
-        Some(Self {
-            x: chars.next()? as u8 - 97,
-            y: chars.next()? as u8 - 49
-        })
-    }
-    pub fn to_string(&self) -> String {
-        std::str::from_utf8(&[(self.x + 97) as u8, (self.y + 49) as u8]).expect("Invalid Coordinate").to_owned()
+        let &[x, y] = string.as_bytes() else { return None; };
+        if !(b'a'..=b'h').contains(&x) || !(b'1'..=b'8').contains(&y) { return None; }
+        Some(Self { x: x - 97, y: y - 49 })
     }
 }
 impl Display for Coordinate {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
-        write!(f, "{}", self.to_string())
+        write!(f, "{}{}", (self.x + 97) as char, (self.y + 49) as char)
     }
 }
 
@@ -39,6 +46,9 @@ fn coordinate_from_string() {
     assert_eq!(Coordinate::from_string("d6"), Some(Coordinate::new(3, 5)));
     assert_eq!(Coordinate::from_string("a1"), Some(Coordinate::new(0, 0)));
     assert_eq!(Coordinate::from_string("h8"), Some(Coordinate::new(7, 7)));
+    assert_eq!(Coordinate::from_string("i1"), None);
+    assert_eq!(Coordinate::from_string("A1"), None);
+    assert_eq!(Coordinate::from_string("a10"), None);
 }
 
 /** moves that are too different from the others, they need special treatment */
@@ -64,27 +74,59 @@ impl Move {
     pub fn from_coordinates(from: Coordinate, to: Coordinate, special_move: Option<SpecialMove>) -> Self {
         Self { from, to, special_move }
     }
-    pub fn to_string(&self) -> String {
-        let mut s = self.from.to_string();
-        s.push_str(&self.to.to_string());
-        s
-    }
+    /**
+        parses the long algebraic form UCI uses, like e2e4 or e7e8q. A promotion letter at the end makes the move
+        a promotion, otherwise it gets the given special move. To find out the special move from a position,
+        use Chessboard::parse_uci_move
+    */
     pub fn from_string(string: &str, special_move: Option<SpecialMove>) -> Option<Self> {
-        if string.len() != 4 { return None; }
-        let coords = string.split_at(2);
-        Some(Self::from_coordinates(Coordinate::from_string(coords.0)?, Coordinate::from_string(coords.1)?, special_move))
+        let (from, to, promotion) = (string.get(0..2)?, string.get(2..4)?, string.get(4..)?);
+        let special_move = match promotion.chars().collect::<Vec<char>>()[..] {
+            [] => special_move,
+            [character] => Some(SpecialMove::Promotion(PieceType::from_char(character).filter(|piece_type| PROMOTION_PIECES.contains(piece_type))?)),
+            _ => return None
+        };
+        Some(Self::from_coordinates(Coordinate::from_string(from)?, Coordinate::from_string(to)?, special_move))
     }
 }
 impl Display for Move {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
-        write!(f, "{}{}", self.from, self.to)
+        write!(f, "{}{}", self.from, self.to)?;
+        if let Some(SpecialMove::Promotion(piece_type)) = self.special_move {
+            write!(f, "{}", piece_type.to_char())?;
+        }
+        Ok(())
     }
 }
 
-#[derive(Debug)]
+#[test]
+fn move_string() {
+    let promotion = Move::new(4, 6, 4, 7, Some(SpecialMove::Promotion(PieceType::Knight)));
+    assert_eq!(promotion.to_string(), "e7e8n");
+    assert_eq!(Move::from_string("e7e8n", None), Some(promotion));
+    assert_eq!(Move::from_string("e1g1", Some(SpecialMove::Castling)), Some(Move::new(4, 0, 6, 0, Some(SpecialMove::Castling))));
+    assert_eq!(Move::from_string("e7e8k", None), None);
+    assert_eq!(Move::from_string("e7e8qq", None), None);
+    assert_eq!(Move::from_string("e7e", None), None);
+}
+
+/** everything Chessboard::make_move overwrites, so that Chessboard::unmake_move can restore it */
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+struct Undo {
+    r#move: Move,
+    captured: Option<Piece>,
+    last_move: Option<Move>,
+    castling: [bool; 4],
+    halfmove_clock: u16,
+    first_repeatable_state: usize
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Chessboard {
     /** \[P, R, N, B, Q, K, p, r, n, b, q, k\] */
     piece_bitboards: [u64; 12],
+    /** Zobrist key of the pieces alone, kept up to date by set_code. Chessboard::hash adds the rest of the position */
+    pieces_hash: u64,
     /** array of rows - each tile represents 4 bits of the u32. */
     state: [u32; 8],
     /** next player to play */
@@ -94,23 +136,30 @@ pub struct Chessboard {
     /** \[white can castle kingside, white can castle queenside, black can castle kingside, black can castle queenside\] */
     pub castling: [bool; 4],
     /** [https://www.chessprogramming.org/Halfmove_Clock] */
-    halfmove_clock: u8,
+    halfmove_clock: u16,
     /** amount of moves since the start of the match */
     pub move_number: u16,
-    /** all the positions (state, to play) since an irreversible move has been made, in order to account for the 3 move rule */
-    previous_states: Vec<([u32; 8], Color)>
+    /** hashes of all the positions reached by making moves on this board, in order to account for the 3 move rule */
+    previous_states: Vec<u64>,
+    /** index of the first position in previous_states made after the last irreversible move, only positions from there on can repeat */
+    first_repeatable_state: usize,
+    /** one entry for every move made on this board, so that they can be taken back */
+    undo_stack: Vec<Undo>
 }
 impl Default for Chessboard {
     fn default() -> Self {
         Self {
             piece_bitboards: [0; 12],
+            pieces_hash: 0,
             state: [0xCCCCCCCC; 8],
             to_play: Color::default(),
             last_move: None,
             castling: [false, false, false, false],
             halfmove_clock: 0,
             move_number: 0,
-            previous_states: vec![]
+            previous_states: vec![],
+            first_repeatable_state: 0,
+            undo_stack: vec![]
         }
     }
 }
@@ -118,92 +167,37 @@ impl Default for Chessboard {
 #[derive(Debug, PartialEq, Eq)]
 pub struct OutsideOfChessboard;
 
-impl Chessboard {
-    // read https://en.wikipedia.org/wiki/Forsyth%E2%80%93Edwards_Notation#Definition if you want
-    // to know better how this works.
-    /**
-        creates a Chessboard from a FEN code. If the FEN code is invalid this function will probably
-        return None (sometimes it can generate a chess board out of invalid FEN)
-    */
-    pub fn from_fen(fen: &str) -> Option<Self> {
-        let mut chessboard = Chessboard::default();
-
-        let mut chars = fen.chars();
-        let mut current = chars.next()?;
-
-        // get the state of the board
-        let mut x = 0;
-        let mut y = 7;
-        while current != ' ' {
-            if let Some(piece) = Piece::from_character(current) {
-                if chessboard.set(x, y, Some(piece)).is_err() { return None; }
-                x += 1;
-            } else if let Some(digit) = current.to_digit(10) {
-                x += digit as u8;
-            } else if current == '/' {
-                y -= 1;
-                x = 0;
-            } else {
-                return None;
-            }
-
-            current = chars.next()?;
-        }
-
-        // get the next player to play
-        if chars.next()? == 'b' { chessboard.to_play = Color::Black; }
-        chars.next();
-        current = chars.next()?;
-        
-        // castling rights
-        while current != ' ' {
-            match current {
-                'K' => chessboard.castling[0] = true,
-                'Q' => chessboard.castling[1] = true,
-                'k' => chessboard.castling[2] = true,
-                'q' => chessboard.castling[3] = true,
-                _ => ()
-            }
-            current = chars.next()?;
-        }
-        current = chars.next()?;
-
-        // if en passant is avaible, put it in last move
-        if current != '-' {
-            let mut coordinate_string = current.to_string();
-            coordinate_string.push(chars.next()?);
-            let coordinate = Coordinate::from_string(&coordinate_string)?;
-            let (x, y) = (coordinate.x, coordinate.y);
-            match y {
-                4 => chessboard.last_move = Some(Move::new(x, 2, x, y, Some(SpecialMove::EnPassant))),
-                5 => chessboard.last_move = Some(Move::new(x, 7, x, y, Some(SpecialMove::EnPassant))),
-                _ => return None
-            }
-        }
-
-        chars.next();
-        current = chars.next()?;
-
-        // half move clock
-        while current != ' ' {
-            if !current.is_digit(10) { return None; }
-            let digit = current.to_digit(10).unwrap();
-            chessboard.halfmove_clock *= 10;
-            chessboard.halfmove_clock += digit as u8;
-            current = chars.next()?;
-        }
+/** the ways a game can end in a draw besides stalemate */
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DrawReason {
+    /** the same position appeared 3 times, either player may claim the draw */
+    ThreefoldRepetition,
+    /** 50 moves by each player without captures or pawn moves, either player may claim the draw */
+    FiftyMoveRule,
+    /** the same position appeared 5 times, the game is drawn automatically */
+    FivefoldRepetition,
+    /** 75 moves by each player without captures or pawn moves, the game is drawn automatically */
+    SeventyFiveMoveRule,
+    /** neither player can possibly checkmate */
+    InsufficientMaterial
+}
 
-        // move number
-        while let Some(current) = chars.next() {
-            if !current.is_digit(10) { return None; }
-            let digit = current.to_digit(10).unwrap();
-            chessboard.move_number *= 10;
-            chessboard.move_number += digit as u16;
-        }
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum GameStatus {
+    Ongoing,
+    Checkmate { winner: Color },
+    Stalemate,
+    Draw(DrawReason)
+}
 
-        Some(chessboard)
-    }
+/** the checks and pins against the king of the player to play, used to filter out moves that leave it in check */
+struct KingSafety {
+    king: u8,
+    checkers: u64,
+    pinned: u64
+}
 
+impl Chessboard {
     pub fn get(&self, x: u8, y: u8) -> Result<Option<Piece>, OutsideOfChessboard> {
         if x > 7 || y > 7 { Err(OutsideOfChessboard) } else { Ok(Piece::from_code(self.get_code(x, y))) }
     }
@@ -215,7 +209,9 @@ impl Chessboard {
         ((self.state[y as usize] >> (4 * x)) % 16) as u8
     }
     pub fn set(&mut self, x: u8, y: u8, piece: Option<Piece>) -> Result<(), OutsideOfChessboard> {
-        if x > 7 || y > 7 { Err(OutsideOfChessboard) } else { Ok( self.set_code(x, y, piece.map(|p| p.to_code()).unwrap_or(12))) }
+        if x > 7 || y > 7 { return Err(OutsideOfChessboard); }
+        self.set_code(x, y, piece.map(|p| p.to_code()).unwrap_or(12));
+        Ok(())
     }
     /**
         very fast, but will panic if x or y are out of bounds. If you don't want that behaviour,
@@ -225,92 +221,336 @@ impl Chessboard {
         let previous_piece_code = self.get_code(x, y);
         if let Some(bitboard) = self.piece_bitboards.get_mut(previous_piece_code as usize) {
             *bitboard ^= 1 << (x + 8 * y);
+            self.pieces_hash ^= zobrist::KEYS.pieces[previous_piece_code as usize][(x + 8 * y) as usize];
         }
         if let Some(bitboard) = self.piece_bitboards.get_mut(code as usize) {
             *bitboard |= 1 << (x + 8 * y);
+            self.pieces_hash ^= zobrist::KEYS.pieces[code as usize][(x + 8 * y) as usize];
         }
         self.state[y as usize] &= !(0b1111 << (4 * x));
         self.state[y as usize] |= (code as u32) << (4 * x);
     }
 
     /**
-        make the move without checking if it's valid. To check for validity, use Chessboard::get_legal_moves. Returns the moved and the captured piece if there were any.
+        make the move without checking if it's valid. To check for validity, use Chessboard::get_legal_moves. Returns the moved and the captured piece if there were any
+        (for en passant, the captured pawn isn't on move.to).
     */
     pub fn make_move(&mut self, r#move: Move) -> (Piece, Option<Piece>) {
-        println!("(castling too) IMPLEMENT EN PASSANT IMPLEMENT EN PASSANT IMPLEMENT EN PASSANT IMPLEMENT EN PASSANT (Chessboard::make_move)");
-
-        let moved = self.get(r#move.from.x, r#move.from.y).expect(&format!("invalid move, move.from = ({}, {})", r#move.from.x, r#move.from.y)).expect("there was no piece to be moved");
-        let captured = self.get(r#move.to.x, r#move.to.y).expect(&format!("invalid move, move.to = ({}, {})", r#move.to.x, r#move.to.y));
-
-        self.to_play = self.to_play.opposite();
-
-        let mut irreversible = false;
+        let moved = self.get(r#move.from.x, r#move.from.y).unwrap_or_else(|_| panic!("invalid move, move.from = ({}, {})", r#move.from.x, r#move.from.y)).expect("there was no piece to be moved");
+        let captured_position = match r#move.special_move {
+            Some(SpecialMove::EnPassant) => Coordinate::new(r#move.to.x, r#move.from.y),
+            _ => r#move.to
+        };
+        let captured = self.get(captured_position.x, captured_position.y).unwrap_or_else(|_| panic!("invalid move, move.to = ({}, {})", r#move.to.x, r#move.to.y));
+
+        self.undo_stack.push(Undo {
+            r#move,
+            captured,
+            last_move: self.last_move,
+            castling: self.castling,
+            halfmove_clock: self.halfmove_clock,
+            first_repeatable_state: self.first_repeatable_state
+        });
+
+        // captures and pawn moves can never be undone, they also reset the halfmove clock
+        let resets_halfmove_clock = moved.piece_type == PieceType::Pawn || captured.is_some();
+        let mut irreversible = resets_halfmove_clock;
 
         // if en passant was avaible, the move is irreversible
-        if let Some(last_move) = self.last_move {
-            if last_move.special_move == Some(SpecialMove::TwoSquareAdvance) && (
-                self.get(last_move.to.x - 1, last_move.to.y).is_ok_and(|piece| piece == Some(Piece::new(self.to_play, PieceType::Pawn))) ||
-                self.get(last_move.to.x - 1, last_move.to.y).is_ok_and(|piece| piece == Some(Piece::new(self.to_play, PieceType::Pawn)))
-            ) {
+        if let Some(last_move) = self.last_move.filter(|last_move| last_move.special_move == Some(SpecialMove::TwoSquareAdvance)) {
+            let pawn = Some(Piece::new(self.to_play, PieceType::Pawn));
+            if [-1, 1].into_iter().any(|dx| self.get((last_move.to.x as i8 + dx) as u8, last_move.to.y) == Ok(pawn)) {
                 irreversible = true;
             }
         }
 
-        // if a pawn was moved or if a player lost castling rights, the move is irreversible
-        match (moved.piece_type, moved.color) {
-            (PieceType::Pawn, _) => irreversible = true,
-            (PieceType::King, Color::White) => {
-                if self.castling[0] || self.castling[1] { irreversible = true; }
-                self.castling[0] = false;
-                self.castling[1] = false;
-            },
-            (PieceType::King, Color::Black) => {
-                if self.castling[2] || self.castling[3] { irreversible = true; }
-                self.castling[2] = false;
-                self.castling[3] = false;
-            },
-            (PieceType::Rook, Color::White) => {
-                if self.castling[0] && r#move.from == Coordinate::new(7, 0) { irreversible = true; self.castling[0] = false; }
-                if self.castling[1] && r#move.from == Coordinate::new(0, 0) { irreversible = true; self.castling[1] = false; }
-            },
-            (PieceType::Rook, Color::Black) => {
-                if self.castling[2] && r#move.from == Coordinate::new(7, 7) { irreversible = true; self.castling[2] = false; }
-                if self.castling[3] && r#move.from == Coordinate::new(0, 7) { irreversible = true; self.castling[3] = false; }
+        // if a player lost castling rights, the move is irreversible. Moving the king loses both of them, and
+        // moving a rook out of its corner or capturing it there loses the right to castle to that side
+        let king_y = if moved.color == Color::White { 0 } else { 7 };
+        for (index, corner) in [(7, 0), (0, 0), (7, 7), (0, 7)].into_iter().enumerate() {
+            let corner = Coordinate::new(corner.0, corner.1);
+            let king_moved = moved.piece_type == PieceType::King && corner.y == king_y;
+            if self.castling[index] && (king_moved || r#move.from == corner || r#move.to == corner) {
+                irreversible = true;
+                self.castling[index] = false;
             }
-            _ => ()
         }
 
-        self.set(r#move.from.x, r#move.from.y, None).unwrap();
-        self.set(r#move.to.x, r#move.to.y, Some(moved)).unwrap();
+        self.set_code(captured_position.x, captured_position.y, 12);
+        self.set_code(r#move.from.x, r#move.from.y, 12);
+        let placed = match r#move.special_move {
+            Some(SpecialMove::Promotion(piece_type)) => Piece::new(moved.color, piece_type),
+            _ => moved
+        };
+        self.set_code(r#move.to.x, r#move.to.y, placed.to_code());
+
+        // the king has already been moved, now move the rook
+        if r#move.special_move == Some(SpecialMove::Castling) {
+            let (rook_from_x, rook_to_x) = if r#move.to.x > r#move.from.x { (7, 5) } else { (0, 3) };
+            let rook_code = self.get_code(rook_from_x, r#move.from.y);
+            self.set_code(rook_from_x, r#move.from.y, 12);
+            self.set_code(rook_to_x, r#move.from.y, rook_code);
+        }
+
+        self.halfmove_clock = if resets_halfmove_clock { 0 } else { self.halfmove_clock.saturating_add(1) };
+        if moved.color == Color::Black { self.move_number += 1; }
+        self.last_move = Some(r#move);
+        self.to_play = self.to_play.opposite();
 
-        if irreversible { self.previous_states.clear(); } else { self.previous_states.push((self.state, self.to_play)) }
+        if irreversible { self.first_repeatable_state = self.previous_states.len(); }
+        self.previous_states.push(self.hash());
 
         (moved, captured)
     }
 
-    pub fn get_legal_moves(&self) -> Vec<Move> {
-        let mut moves = vec![];
+    /**
+        takes back the last move made with Chessboard::make_move, restoring the position exactly as it was.
+        Returns the move that was taken back, or None if there is no move to take back.
+    */
+    pub fn unmake_move(&mut self) -> Option<Move> {
+        let Undo { r#move, captured, last_move, castling, halfmove_clock, first_repeatable_state } = self.undo_stack.pop()?;
 
-        for y in 0..8 {
-            for x in 0..8 {
-                if let Some(piece) = self.get(x, y).expect("DONT MESS WITH THE CODE").filter(|piece| piece.color == self.to_play ) {
-                    piece.append_legal_moves(Coordinate::new(x, y), self, &mut moves);
-                }
-            }
+        self.to_play = self.to_play.opposite();
+        let moved = match r#move.special_move {
+            Some(SpecialMove::Promotion(_)) => Piece::new(self.to_play, PieceType::Pawn),
+            _ => Piece::from_code(self.get_code(r#move.to.x, r#move.to.y)).expect("the moved piece has disappeared")
+        };
+        let captured_position = match r#move.special_move {
+            Some(SpecialMove::EnPassant) => Coordinate::new(r#move.to.x, r#move.from.y),
+            _ => r#move.to
+        };
+
+        self.set_code(r#move.to.x, r#move.to.y, 12);
+        self.set_code(captured_position.x, captured_position.y, captured.map(|piece| piece.to_code()).unwrap_or(12));
+        self.set_code(r#move.from.x, r#move.from.y, moved.to_code());
+
+        if r#move.special_move == Some(SpecialMove::Castling) {
+            let (rook_from_x, rook_to_x) = if r#move.to.x > r#move.from.x { (7, 5) } else { (0, 3) };
+            let rook_code = self.get_code(rook_to_x, r#move.from.y);
+            self.set_code(rook_to_x, r#move.from.y, 12);
+            self.set_code(rook_from_x, r#move.from.y, rook_code);
         }
 
+        if self.to_play == Color::Black { self.move_number -= 1; }
+        self.last_move = last_move;
+        self.castling = castling;
+        self.halfmove_clock = halfmove_clock;
+        self.first_repeatable_state = first_repeatable_state;
+        self.previous_states.pop();
+
+        Some(r#move)
+    }
+
+    /**
+        64 bit Zobrist key of the position: the pieces, the player to play, the castling rights and the file
+        of the en passant target if a pawn of the player to play stands next to the pawn that can be captured
+    */
+    pub fn hash(&self) -> u64 {
+        let mut hash = self.pieces_hash;
+        if self.to_play == Color::Black { hash ^= zobrist::KEYS.black_to_play; }
+        for (index, _) in self.castling.iter().enumerate().filter(|(_, can_castle)| **can_castle) {
+            hash ^= zobrist::KEYS.castling[index];
+        }
+        if let Some(file) = self.en_passant_file() { hash ^= zobrist::KEYS.en_passant[file as usize]; }
+        hash
+    }
+    /** the file of the en passant target, only if a pawn of the player to play stands next to the pawn that can be captured */
+    pub(crate) fn en_passant_file(&self) -> Option<u8> {
+        let target = self.en_passant_target()?;
+        let capturers = bitboard::pawn_attacks(bitboard::square_index(target), self.to_play.opposite()) & self.pieces(PieceType::Pawn, self.to_play);
+        (capturers != 0).then_some(target.x)
+    }
+
+    /** the square a pawn would land on by capturing en passant, if the last move was a two square advance */
+    pub fn en_passant_target(&self) -> Option<Coordinate> {
+        self.last_move
+            .filter(|last_move| last_move.special_move == Some(SpecialMove::TwoSquareAdvance))
+            .map(|last_move| Coordinate::new(last_move.to.x, (last_move.from.y + last_move.to.y) / 2))
+    }
+
+    /** all the moves of the player to play, including the ones that leave their own king in check */
+    pub fn get_pseudo_legal_moves(&self) -> Vec<Move> {
+        let mut moves = Vec::with_capacity(64);
+        self.append_pseudo_legal_moves(&mut moves);
         moves
     }
 
-    pub fn is_legal_move(&self, r#move: Move) -> Result<bool, OutsideOfChessboard> {
+    /** all the moves the player to play can make without leaving their own king in check */
+    pub fn get_legal_moves(&self) -> Vec<Move> {
+        let mut moves = self.get_pseudo_legal_moves();
+        if let Some(king_safety) = self.king_safety() {
+            moves.retain(|r#move| self.keeps_king_safe(*r#move, &king_safety));
+        }
+        moves
+    }
+
+    pub fn is_pseudo_legal_move(&self, r#move: Move) -> Result<bool, OutsideOfChessboard> {
+        self.get(r#move.to.x, r#move.to.y)?;
         Ok(self.get(r#move.from.x, r#move.from.y)?.map(|piece| piece.color == self.to_play && piece.is_legal_move(r#move, self)).unwrap_or(false))
     }
+
+    pub fn is_legal_move(&self, r#move: Move) -> Result<bool, OutsideOfChessboard> {
+        Ok(self.is_pseudo_legal_move(r#move)? && self.king_safety().is_none_or(|king_safety| self.keeps_king_safe(r#move, &king_safety)))
+    }
+
+    /** checks if the king of the player to play is attacked */
+    pub fn is_in_check(&self) -> bool {
+        self.checkers() != 0
+    }
+
+    /** amount of times the current position has appeared since the last irreversible move, counting the current one */
+    pub fn repetitions(&self) -> usize {
+        let current = self.hash();
+        self.previous_states[self.first_repeatable_state..].iter().filter(|hash| **hash == current).count().max(1)
+    }
+    /** checks if any position since the last irreversible move appeared twice, not only the current one */
+    pub fn has_repeated(&self) -> bool {
+        let states = &self.previous_states[self.first_repeatable_state..];
+        states.iter().enumerate().any(|(index, hash)| states[..index].contains(hash))
+    }
+
+    /**
+        checks if neither player can checkmate by any series of legal moves: only kings and at most one minor
+        piece are left, or every remaining bishop stands on squares of the same color
+    */
+    pub fn has_insufficient_material(&self) -> bool {
+        let pieces = |piece_type: PieceType| self.pieces(piece_type, Color::White) | self.pieces(piece_type, Color::Black);
+
+        if (pieces(PieceType::Pawn) | pieces(PieceType::Rook) | pieces(PieceType::Queen)) != 0 { return false; }
+        let (knights, bishops) = (pieces(PieceType::Knight), pieces(PieceType::Bishop));
+        if (knights | bishops).count_ones() <= 1 { return true; }
+
+        const LIGHT_SQUARES: u64 = 0x55AA55AA55AA55AA;
+        knights == 0 && (bishops & LIGHT_SQUARES == 0 || bishops & !LIGHT_SQUARES == 0)
+    }
+
+    /** the plies played since the last capture or pawn move, which the 50 move rule counts */
+    pub fn halfmove_clock(&self) -> u16 {
+        self.halfmove_clock
+    }
+
+    /** the draw the player to play may claim right now, if any */
+    pub fn claimable_draw(&self) -> Option<DrawReason> {
+        if self.repetitions() >= 3 { return Some(DrawReason::ThreefoldRepetition); }
+        if self.halfmove_clock >= 100 { return Some(DrawReason::FiftyMoveRule); }
+        None
+    }
+
+    /**
+        reports whether the game is over without anyone claiming anything: checkmate, stalemate and the draws FIDE
+        rules apply automatically (fivefold repetition, the 75 move rule and insufficient material). Draws that
+        have to be claimed are reported by Chessboard::claimable_draw
+    */
+    pub fn game_status(&self) -> GameStatus {
+        if self.get_legal_moves().is_empty() {
+            return if self.is_in_check() { GameStatus::Checkmate { winner: self.to_play.opposite() } } else { GameStatus::Stalemate };
+        }
+        if self.repetitions() >= 5 { return GameStatus::Draw(DrawReason::FivefoldRepetition); }
+        if self.halfmove_clock >= 150 { return GameStatus::Draw(DrawReason::SeventyFiveMoveRule); }
+        if self.has_insufficient_material() { return GameStatus::Draw(DrawReason::InsufficientMaterial); }
+        GameStatus::Ongoing
+    }
+
+    pub(crate) fn pieces(&self, piece_type: PieceType, color: Color) -> u64 {
+        self.piece_bitboards[Piece::new(color, piece_type).to_code() as usize]
+    }
+    pub(crate) fn color_occupancy(&self, color: Color) -> u64 {
+        let first = Piece::new(color, PieceType::Pawn).to_code() as usize;
+        self.piece_bitboards[first..first + 6].iter().fold(0, |occupancy, bitboard| occupancy | bitboard)
+    }
+    pub(crate) fn occupancy(&self) -> u64 {
+        self.color_occupancy(Color::White) | self.color_occupancy(Color::Black)
+    }
+    pub(crate) fn king_square(&self, color: Color) -> Option<u8> {
+        let king = self.pieces(PieceType::King, color);
+        if king == 0 { None } else { Some(king.trailing_zeros() as u8) }
+    }
+
+    /** the pieces of the color that would attack the square if the board was occupied as in the occupancy bitboard */
+    fn attackers_with_occupancy(&self, square: u8, color: Color, occupancy: u64) -> u64 {
+        let rooks = self.pieces(PieceType::Rook, color) | self.pieces(PieceType::Queen, color);
+        let bishops = self.pieces(PieceType::Bishop, color) | self.pieces(PieceType::Queen, color);
+        // a pawn attacks the square if a pawn of the other color standing on that square would attack the pawn
+        (bitboard::pawn_attacks(square, color.opposite()) & self.pieces(PieceType::Pawn, color))
+            | (bitboard::knight_attacks(square) & self.pieces(PieceType::Knight, color))
+            | (bitboard::king_attacks(square) & self.pieces(PieceType::King, color))
+            | (bitboard::rook_attacks(square, occupancy) & rooks)
+            | (bitboard::bishop_attacks(square, occupancy) & bishops)
+    }
+
+    /** bitboard (bit x + 8 * y) of the pieces of the color attacking the square */
+    pub fn attackers_to(&self, square: Coordinate, color: Color) -> u64 {
+        self.attackers_with_occupancy(bitboard::square_index(square), color, self.occupancy())
+    }
+
+    /** checks if any piece of the color `by` attacks the square */
+    pub fn is_square_attacked(&self, square: Coordinate, by: Color) -> bool {
+        self.attackers_to(square, by) != 0
+    }
+
+    /** bitboard (bit x + 8 * y) of the enemy pieces giving check to the king of the player to play */
+    pub fn checkers(&self) -> u64 {
+        self.king_square(self.to_play).map_or(0, |king| self.attackers_with_occupancy(king, self.to_play.opposite(), self.occupancy()))
+    }
+
+    /** bitboard (bit x + 8 * y) of the pieces of the player to play that can't leave the line between their king and an enemy sliding piece */
+    pub fn pinned_pieces(&self) -> u64 {
+        let Some(king) = self.king_square(self.to_play) else { return 0; };
+        let enemy = self.to_play.opposite();
+        let (own, occupancy) = (self.color_occupancy(self.to_play), self.occupancy());
+
+        // enemy sliding pieces that would attack the king if nothing stood in between
+        let snipers = (bitboard::rook_attacks(king, 0) & (self.pieces(PieceType::Rook, enemy) | self.pieces(PieceType::Queen, enemy)))
+            | (bitboard::bishop_attacks(king, 0) & (self.pieces(PieceType::Bishop, enemy) | self.pieces(PieceType::Queen, enemy)));
+
+        let mut pinned = 0;
+        for sniper in bitboard::coordinates(snipers) {
+            let blockers = bitboard::between(king, bitboard::square_index(sniper)) & occupancy;
+            if blockers.count_ones() == 1 && blockers & own != 0 { pinned |= blockers; }
+        }
+        pinned
+    }
+
+    fn king_safety(&self) -> Option<KingSafety> {
+        Some(KingSafety { king: self.king_square(self.to_play)?, checkers: self.checkers(), pinned: self.pinned_pieces() })
+    }
+
+    /** checks if a pseudo legal move doesn't leave the king of the player to play in check */
+    fn keeps_king_safe(&self, r#move: Move, king_safety: &KingSafety) -> bool {
+        let KingSafety { king, checkers, pinned } = *king_safety;
+        let enemy = self.to_play.opposite();
+        let (from, to) = (bitboard::from_coordinate(r#move.from), bitboard::from_coordinate(r#move.to));
+
+        if from == 1 << king {
+            // castling moves are only generated when the king is safe along the whole way
+            if r#move.special_move == Some(SpecialMove::Castling) { return true; }
+            // the king doesn't block the sliding pieces attacking it anymore once it moves
+            return self.attackers_with_occupancy(bitboard::square_index(r#move.to), enemy, self.occupancy() & !from) == 0;
+        }
+
+        // in double check only the king can move
+        if checkers.count_ones() > 1 { return false; }
+
+        if r#move.special_move == Some(SpecialMove::EnPassant) {
+            // two pieces leave the rank at once, so just look at the resulting occupancy
+            let captured = bitboard::from_coordinate(Coordinate::new(r#move.to.x, r#move.from.y));
+            let occupancy = (self.occupancy() & !from & !captured) | to;
+            return self.attackers_with_occupancy(king, enemy, occupancy) & !captured == 0;
+        }
+
+        // the check has to be blocked or the checking piece has to be captured
+        if checkers != 0 && to & (checkers | bitboard::between(king, checkers.trailing_zeros() as u8)) == 0 { return false; }
+
+        // a pinned piece can only move along the line through its king and the piece pinning it
+        let (from_square, to_square) = (bitboard::square_index(r#move.from), bitboard::square_index(r#move.to));
+        pinned & from == 0 || bitboard::between(king, to_square) & from != 0 || bitboard::between(king, from_square) & to != 0
+    }
 }
 
 impl Display for Chessboard {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
         for y in (0..8).rev() {
-            write!(f, "+---+---+---+---+---+---+---+---+\n")?;
+            writeln!(f, "+---+---+---+---+---+---+---+---+")?;
             for x in 0..8 {
                 if let Some(piece) = self.get(x, y).expect("DONT MESS WITH THE CODE") {
                     write!(f, "| {piece} ")?;
@@ -319,9 +559,215 @@ impl Display for Chessboard {
                 }
                 if x == 7 { write!(f, "| {}", y + 1)?; }
             }
-            write!(f, "\n")?;
+            writeln!(f)?;
         }
         write!(f, "+---+---+---+---+---+---+---+---+\n  a   b   c   d   e   f   g   h")?;
         Ok(())
     }
 }
+
+#[test]
+fn legal_moves_agree_with_is_legal_move() {
+    let special_moves = [
+        None,
+        Some(SpecialMove::EnPassant),
+        Some(SpecialMove::Castling),
+        Some(SpecialMove::TwoSquareAdvance),
+        Some(SpecialMove::Promotion(PieceType::Queen)),
+        Some(SpecialMove::Promotion(PieceType::Knight))
+    ];
+    for fen in [
+        "8/8/8/8/2n5/n7/3N4/1N6 b - - 0 1",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        "8/8/8/KPp4r/8/8/8/7k w - c6 0 1",
+        "4k3/8/8/8/8/8/3p4/R3K2R b KQ - 0 1"
+    ] {
+        let chessboard = Chessboard::from_fen(fen).expect("FEN is invalid");
+        let pseudo_legal_moves = chessboard.get_pseudo_legal_moves();
+        let legal_moves = chessboard.get_legal_moves();
+
+        // check all 4096 moves
+        for start_y in 0..8 {
+            for start_x in 0..8 {
+                for end_y in 0..8 {
+                    for end_x in 0..8 {
+                        for special_move in special_moves {
+                            let r#move = Move::new(start_x, start_y, end_x, end_y, special_move);
+                            assert_eq!(chessboard.is_pseudo_legal_move(r#move), Ok(pseudo_legal_moves.contains(&r#move)), "{fen} {move}");
+                            assert_eq!(chessboard.is_legal_move(r#move), Ok(legal_moves.contains(&r#move)), "{fen} {move}");
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn legal_moves_leave_the_king_safe() {
+    let count = |fen: &str| Chessboard::from_fen(fen).expect("FEN is invalid").get_legal_moves().len();
+
+    assert_eq!(count("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"), 20);
+    assert_eq!(count("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1"), 48);
+    assert_eq!(count("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1"), 14);
+    assert_eq!(count("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1"), 6);
+    assert_eq!(count("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8"), 44);
+    assert_eq!(count("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10"), 46);
+
+    // en passant would expose the king to the rook
+    assert_eq!(count("8/8/8/KPp4r/8/8/8/7k w - c6 0 1"), 4);
+    // double check, only the king can move
+    assert_eq!(count("4k3/8/8/8/8/5n2/8/R3K2r w - - 0 1"), 2);
+    // the pinned knight can't move and the pinned rook only slides along the pin
+    assert_eq!(count("4k3/4r3/8/b7/8/4R3/3N4/4K3 w - - 0 1"), 9);
+}
+
+#[test]
+fn make_move_special_moves() {
+    let mut chessboard = Chessboard::from_fen("r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 3 20").expect("FEN is invalid");
+
+    assert_eq!(chessboard.make_move(Move::new(4, 4, 3, 5, Some(SpecialMove::EnPassant))), (
+        Piece::new(Color::White, PieceType::Pawn),
+        Some(Piece::new(Color::Black, PieceType::Pawn))
+    ));
+    assert_eq!(chessboard.get(3, 4), Ok(None));
+    assert_eq!(chessboard.get(3, 5), Ok(Some(Piece::new(Color::White, PieceType::Pawn))));
+    assert_eq!((chessboard.halfmove_clock, chessboard.move_number, chessboard.to_play), (0, 20, Color::Black));
+
+    chessboard.make_move(Move::new(4, 7, 2, 7, Some(SpecialMove::Castling)));
+    assert_eq!(chessboard.get(2, 7), Ok(Some(Piece::new(Color::Black, PieceType::King))));
+    assert_eq!(chessboard.get(3, 7), Ok(Some(Piece::new(Color::Black, PieceType::Rook))));
+    assert_eq!(chessboard.get(0, 7), Ok(None));
+    assert_eq!(chessboard.castling, [true, true, false, false]);
+    assert_eq!((chessboard.halfmove_clock, chessboard.move_number), (1, 21));
+
+    // capturing the rook in its corner takes away the right to castle with it
+    assert_eq!(chessboard.make_move(Move::new(1, 6, 1, 7, Some(SpecialMove::Promotion(PieceType::Knight)))).1, None);
+    assert_eq!(chessboard.get(1, 7), Ok(Some(Piece::new(Color::White, PieceType::Knight))));
+    chessboard.make_move(Move::new(7, 7, 7, 0, None));
+    assert_eq!(chessboard.castling, [false, true, false, false]);
+
+    chessboard.make_move(Move::new(4, 0, 2, 0, Some(SpecialMove::Castling)));
+    assert_eq!(chessboard.get(2, 0), Ok(Some(Piece::new(Color::White, PieceType::King))));
+    assert_eq!(chessboard.get(3, 0), Ok(Some(Piece::new(Color::White, PieceType::Rook))));
+    assert_eq!(chessboard.castling, [false, false, false, false]);
+    assert_eq!(chessboard.last_move, Some(Move::new(4, 0, 2, 0, Some(SpecialMove::Castling))));
+}
+
+#[test]
+fn unmake_move_restores_the_position() {
+    for fen in [
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        "8/8/8/KPp4r/8/8/8/7k w - c6 0 1"
+    ] {
+        let mut chessboard = Chessboard::from_fen(fen).expect("FEN is invalid");
+        let original = chessboard.clone();
+        for r#move in chessboard.get_legal_moves() {
+            chessboard.make_move(r#move);
+            let after_move = chessboard.clone();
+            for reply in chessboard.get_legal_moves() {
+                chessboard.make_move(reply);
+                assert_eq!(chessboard.unmake_move(), Some(reply));
+                assert_eq!(chessboard, after_move, "{fen} {move} {reply}");
+            }
+            assert_eq!(chessboard.unmake_move(), Some(r#move));
+            assert_eq!(chessboard, original, "{fen} {move}");
+        }
+        assert_eq!(chessboard.unmake_move(), None);
+    }
+}
+
+#[test]
+fn game_status() {
+    let status = |fen: &str| Chessboard::from_fen(fen).expect("FEN is invalid").game_status();
+
+    assert_eq!(status("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"), GameStatus::Ongoing);
+    assert_eq!(status("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3"), GameStatus::Checkmate { winner: Color::Black });
+    assert_eq!(status("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1"), GameStatus::Stalemate);
+    assert_eq!(status("7k/8/6K1/8/8/8/8/R7 w - - 150 120"), GameStatus::Draw(DrawReason::SeventyFiveMoveRule));
+    // checkmate takes precedence over the 75 move rule
+    assert_eq!(status("R6k/8/6K1/8/8/8/8/8 b - - 150 120"), GameStatus::Checkmate { winner: Color::White });
+
+    for fen in ["8/8/3k4/8/8/3K4/8/8 w - - 0 1", "8/8/3k4/8/8/3K4/3N4/8 w - - 0 1", "8/2b5/3k4/8/8/3K4/3B4/8 w - - 0 1"] {
+        assert_eq!(status(fen), GameStatus::Draw(DrawReason::InsufficientMaterial), "{fen}");
+    }
+    for fen in ["8/3b4/3k4/8/8/3K4/3B4/8 w - - 0 1", "8/3n4/3k4/8/8/3K4/3N4/8 w - - 0 1", "8/8/3k4/8/8/3K4/3P4/8 w - - 0 1"] {
+        assert_eq!(status(fen), GameStatus::Ongoing, "{fen}");
+    }
+
+    let mut chessboard = Chessboard::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").expect("FEN is invalid");
+    let knight_moves = [Move::new(6, 0, 5, 2, None), Move::new(6, 7, 5, 5, None), Move::new(5, 2, 6, 0, None), Move::new(5, 5, 6, 7, None)];
+    assert!(!chessboard.has_repeated());
+    for repetition in 2..=5 {
+        for r#move in knight_moves { chessboard.make_move(r#move); }
+        assert_eq!(chessboard.repetitions(), repetition);
+        assert_eq!(chessboard.claimable_draw(), Some(DrawReason::ThreefoldRepetition).filter(|_| repetition >= 3));
+    }
+    assert_eq!(chessboard.game_status(), GameStatus::Draw(DrawReason::FivefoldRepetition));
+    chessboard.unmake_move();
+    assert_eq!(chessboard.game_status(), GameStatus::Ongoing);
+    // the knight moving on doesn't undo the repetitions before it, but a pawn move does
+    chessboard.make_move(Move::new(5, 5, 7, 4, None));
+    assert_eq!((chessboard.repetitions(), chessboard.has_repeated()), (1, true));
+    chessboard.make_move(Move::new(4, 1, 4, 2, None));
+    assert!(!chessboard.has_repeated());
+
+    let chessboard = Chessboard::from_fen("7k/8/6K1/8/8/8/8/R7 w - - 100 80").expect("FEN is invalid");
+    assert_eq!((chessboard.claimable_draw(), chessboard.game_status()), (Some(DrawReason::FiftyMoveRule), GameStatus::Ongoing));
+}
+
+#[test]
+fn attack_queries() {
+    let bitboard = |names: &[&str]| names.iter().fold(0, |bitboard, name| bitboard | bitboard::from_coordinate(Coordinate::from_string(name).unwrap()));
+    let square = |name: &str| Coordinate::from_string(name).unwrap();
+
+    let chessboard = Chessboard::from_fen("4k3/8/8/1b6/4r3/3n4/3N4/4K3 w - - 0 1").expect("FEN is invalid");
+    assert_eq!(chessboard.checkers(), bitboard(&["d3", "e4"]));
+    assert_eq!(chessboard.attackers_to(square("c1"), Color::Black), bitboard(&["d3"]));
+    assert_eq!(chessboard.attackers_to(square("d4"), Color::Black), bitboard(&["e4"]));
+    assert_eq!(chessboard.attackers_to(square("d4"), Color::White), 0);
+    assert!(chessboard.is_square_attacked(square("e2"), Color::Black));
+    assert!(!chessboard.is_square_attacked(square("d1"), Color::Black));
+    assert!(chessboard.is_in_check());
+
+    let chessboard = Chessboard::from_fen("4k3/4r3/8/b7/8/4R3/3NP3/4K3 w - - 0 1").expect("FEN is invalid");
+    assert_eq!(chessboard.checkers(), 0);
+    assert_eq!(chessboard.pinned_pieces(), bitboard(&["d2"]));
+    assert_eq!(chessboard.attackers_to(square("e8"), Color::White), 0);
+
+    let chessboard = Chessboard::from_fen("4k3/4r3/8/b7/8/4R3/3N4/4K3 w - - 0 1").expect("FEN is invalid");
+    assert_eq!(chessboard.pinned_pieces(), bitboard(&["d2", "e3"]));
+}
+
+#[test]
+fn zobrist_hash() {
+    let hash = |fen: &str| Chessboard::from_fen(fen).expect("FEN is invalid").hash();
+
+    let mut chessboard = Chessboard::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").expect("FEN is invalid");
+    let start = chessboard.hash();
+    for r#move in [Move::new(6, 0, 5, 2, None), Move::new(1, 7, 2, 5, None), Move::new(1, 0, 2, 2, None), Move::new(6, 7, 5, 5, None)] {
+        chessboard.make_move(r#move);
+    }
+    assert_eq!(chessboard.hash(), hash("r1bqkb1r/pppppppp/2n2n2/8/8/2N2N2/PPPPPPPP/R1BQKB1R w KQkq - 4 3"));
+    for _ in 0..4 { chessboard.unmake_move(); }
+    assert_eq!(chessboard.hash(), start);
+
+    assert_ne!(hash("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1"), hash("r3k2r/8/8/8/8/8/8/R3K2R w Qkq - 0 1"));
+    assert_ne!(hash("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1"), hash("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1"));
+    assert_ne!(hash("4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1"), hash("4k3/8/8/8/3pP3/8/8/4K3 b - - 0 1"));
+    // nobody can capture en passant, so the target doesn't matter
+    assert_eq!(hash("4k3/8/8/8/4P3/8/8/4K3 b - e3 0 1"), hash("4k3/8/8/8/4P3/8/8/4K3 b - - 0 1"));
+
+    // the first position had an en passant capture available, so it isn't repeated by the following ones
+    let mut chessboard = Chessboard::from_fen("4k3/8/8/8/3p4/8/4P3/4K3 w - - 0 1").expect("FEN is invalid");
+    chessboard.make_move(Move::new(4, 1, 4, 3, Some(SpecialMove::TwoSquareAdvance)));
+    for _ in 0..2 {
+        for r#move in [Move::new(4, 7, 3, 7, None), Move::new(4, 0, 3, 0, None), Move::new(3, 7, 4, 7, None), Move::new(3, 0, 4, 0, None)] {
+            chessboard.make_move(r#move);
+        }
+    }
+    assert_eq!(chessboard.repetitions(), 2);
+}
@@ -0,0 +1,217 @@
+use std::fmt::{Display, Formatter, Error};
+
+use crate::pieces::{Piece, Color, PieceType};
+
+use super::{Chessboard, Coordinate, Move, SpecialMove};
+
+/** everything that can be wrong with a FEN code */
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum FenError {
+    /** a FEN code has 6 fields separated by spaces, this is how many were found */
+    WrongFieldCount(usize),
+    /** the piece placement has to describe 8 ranks, this is how many were found */
+    WrongRankCount(usize),
+    /** the rank (1 to 8) doesn't describe exactly 8 squares */
+    BadRankLength(u8),
+    UnknownPiece(char),
+    BadSideToMove(String),
+    MalformedCastling(String),
+    /** the castling right (K, Q, k or q) doesn't have its king and rook on their initial squares */
+    ImpossibleCastling(char),
+    /** the en passant target isn't a square right behind a pawn that could have just advanced two squares */
+    ImpossibleEnPassant(String),
+    BadHalfmoveClock(String),
+    BadMoveNumber(String)
+}
+
+impl Display for FenError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match self {
+            FenError::WrongFieldCount(count) => write!(f, "expected 6 fields, found {count}"),
+            FenError::WrongRankCount(count) => write!(f, "expected 8 ranks, found {count}"),
+            FenError::BadRankLength(rank) => write!(f, "rank {rank} doesn't have 8 squares"),
+            FenError::UnknownPiece(character) => write!(f, "unknown piece '{character}'"),
+            FenError::BadSideToMove(side) => write!(f, "the side to move has to be 'w' or 'b', found '{side}'"),
+            FenError::MalformedCastling(castling) => write!(f, "malformed castling rights '{castling}'"),
+            FenError::ImpossibleCastling(right) => write!(f, "castling right '{right}' without the king and rook on their initial squares"),
+            FenError::ImpossibleEnPassant(square) => write!(f, "impossible en passant target '{square}'"),
+            FenError::BadHalfmoveClock(clock) => write!(f, "bad halfmove clock '{clock}'"),
+            FenError::BadMoveNumber(number) => write!(f, "bad move number '{number}'")
+        }
+    }
+}
+
+impl std::error::Error for FenError {}
+
+pub const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+/** the castling rights in the order of Chessboard::castling, with the rank of the king and the file of the rook they need */
+const CASTLING_RIGHTS: [(char, u8, u8); 4] = [('K', 0, 7), ('Q', 0, 0), ('k', 7, 7), ('q', 7, 0)];
+
+impl Chessboard {
+    // read https://en.wikipedia.org/wiki/Forsyth%E2%80%93Edwards_Notation#Definition if you want
+    // to know better how this works.
+    /** creates a Chessboard from a FEN code, rejecting anything that isn't exactly a valid FEN code */
+    pub fn from_fen(fen: &str) -> Result<Self, FenError> {
+        let fields: Vec<&str> = fen.split(' ').collect();
+        let [placement, side_to_move, castling, en_passant, halfmove_clock, move_number] = fields[..] else {
+            return Err(FenError::WrongFieldCount(fields.len()));
+        };
+
+        let mut chessboard = Chessboard::default();
+
+        // get the state of the board
+        let ranks: Vec<&str> = placement.split('/').collect();
+        if ranks.len() != 8 { return Err(FenError::WrongRankCount(ranks.len())); }
+        for (rank, y) in ranks.into_iter().zip((0..8).rev()) {
+            let mut x = 0;
+            let mut previous_was_digit = false;
+            for current in rank.chars() {
+                if let Some(digit) = current.to_digit(10) {
+                    // "44" describes 8 squares, but nobody writes it like that
+                    if !(1..=8).contains(&digit) || previous_was_digit { return Err(FenError::BadRankLength(y + 1)); }
+                    x += digit as u8;
+                    previous_was_digit = true;
+                } else {
+                    let piece = Piece::from_character(current).ok_or(FenError::UnknownPiece(current))?;
+                    chessboard.set(x, y, Some(piece)).map_err(|_| FenError::BadRankLength(y + 1))?;
+                    x += 1;
+                    previous_was_digit = false;
+                }
+                if x > 8 { return Err(FenError::BadRankLength(y + 1)); }
+            }
+            if x != 8 { return Err(FenError::BadRankLength(y + 1)); }
+        }
+
+        // get the next player to play
+        chessboard.to_play = match side_to_move {
+            "w" => Color::White,
+            "b" => Color::Black,
+            _ => return Err(FenError::BadSideToMove(side_to_move.to_owned()))
+        };
+
+        // castling rights, always in the order KQkq
+        if castling.is_empty() { return Err(FenError::MalformedCastling(castling.to_owned())); }
+        if castling != "-" {
+            let mut rights = CASTLING_RIGHTS.iter().enumerate().peekable();
+            for current in castling.chars() {
+                while rights.next_if(|(_, (right, _, _))| *right != current).is_some() {}
+                let Some((index, (right, y, rook_x))) = rights.next() else {
+                    return Err(FenError::MalformedCastling(castling.to_owned()));
+                };
+                let color = Color::from_char(*right);
+                if chessboard.get(4, *y) != Ok(Some(Piece::new(color, PieceType::King))) ||
+                    chessboard.get(*rook_x, *y) != Ok(Some(Piece::new(color, PieceType::Rook))) {
+                    return Err(FenError::ImpossibleCastling(*right));
+                }
+                chessboard.castling[index] = true;
+            }
+        }
+
+        // if en passant is avaible, put it in last move
+        if en_passant != "-" {
+            let impossible = || FenError::ImpossibleEnPassant(en_passant.to_owned());
+            let target = Coordinate::from_string(en_passant).ok_or_else(impossible)?;
+            // (rank the pawn came from, rank it landed on) for the player who just moved
+            let (from_y, to_y) = match (chessboard.to_play, target.y) {
+                (Color::Black, 2) => (1, 3),
+                (Color::White, 5) => (6, 4),
+                _ => return Err(impossible())
+            };
+            let pawn = Piece::new(chessboard.to_play.opposite(), PieceType::Pawn);
+            if chessboard.get(target.x, from_y) != Ok(None) || chessboard.get(target.x, target.y) != Ok(None) ||
+                chessboard.get(target.x, to_y) != Ok(Some(pawn)) {
+                return Err(impossible());
+            }
+            chessboard.last_move = Some(Move::new(target.x, from_y, target.x, to_y, Some(SpecialMove::TwoSquareAdvance)));
+        }
+
+        let parse_number = |field: &str| if field.chars().all(|current| current.is_ascii_digit()) { field.parse::<u16>().ok() } else { None };
+        chessboard.halfmove_clock = parse_number(halfmove_clock).ok_or_else(|| FenError::BadHalfmoveClock(halfmove_clock.to_owned()))?;
+        chessboard.move_number = parse_number(move_number)
+            .filter(|number| *number > 0)
+            .ok_or_else(|| FenError::BadMoveNumber(move_number.to_owned()))?;
+
+        chessboard.previous_states.push(chessboard.hash());
+        Ok(chessboard)
+    }
+
+    /** the FEN code of the position, Chessboard::from_fen(&chessboard.to_fen()) gives back the same position */
+    pub fn to_fen(&self) -> String {
+        let mut fen = String::new();
+
+        for y in (0..8).rev() {
+            let mut empty_squares = 0;
+            for x in 0..8 {
+                match self.get(x, y).expect("DONT MESS WITH THE CODE") {
+                    Some(piece) => {
+                        if empty_squares > 0 { fen.push_str(&empty_squares.to_string()); }
+                        empty_squares = 0;
+                        fen.push(piece.to_character());
+                    }
+                    None => empty_squares += 1
+                }
+            }
+            if empty_squares > 0 { fen.push_str(&empty_squares.to_string()); }
+            if y > 0 { fen.push('/'); }
+        }
+
+        fen.push_str(if self.to_play == Color::White { " w " } else { " b " });
+
+        let castling: String = CASTLING_RIGHTS.iter().zip(self.castling).filter(|(_, can_castle)| *can_castle).map(|((right, _, _), _)| *right).collect();
+        fen.push_str(if castling.is_empty() { "-" } else { &castling });
+
+        match self.en_passant_target() {
+            Some(target) => fen.push_str(&format!(" {target}")),
+            None => fen.push_str(" -")
+        }
+
+        fen.push_str(&format!(" {} {}", self.halfmove_clock, self.move_number));
+        fen
+    }
+}
+
+#[test]
+fn fen_round_trip() {
+    for fen in [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        "8/8/8/KPp4r/8/8/8/7k w - c6 0 1",
+        "4k3/8/8/8/8/8/8/4K2R w K - 300 1000"
+    ] {
+        assert_eq!(Chessboard::from_fen(fen).expect("FEN is invalid").to_fen(), fen);
+    }
+
+    let mut chessboard = Chessboard::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").expect("FEN is invalid");
+    chessboard.make_move(Move::new(4, 1, 4, 3, Some(SpecialMove::TwoSquareAdvance)));
+    chessboard.make_move(Move::new(6, 7, 5, 5, None));
+    assert_eq!(chessboard.to_fen(), "rnbqkb1r/pppppppp/5n2/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 1 2");
+}
+
+#[test]
+fn fen_errors() {
+    let error = |fen: &str| Chessboard::from_fen(fen).expect_err(fen);
+
+    assert_eq!(error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -"), FenError::WrongFieldCount(4));
+    assert_eq!(error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR  w KQkq - 0 1"), FenError::WrongFieldCount(7));
+    assert_eq!(error("rnbqkbnr/pppppppp/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"), FenError::WrongRankCount(7));
+    assert_eq!(error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPP/RNBQKBNR w KQkq - 0 1"), FenError::BadRankLength(2));
+    assert_eq!(error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPPP/RNBQKBNR w KQkq - 0 1"), FenError::BadRankLength(2));
+    assert_eq!(error("rnbqkbnr/pppppppp/8/8/44/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"), FenError::BadRankLength(4));
+    assert_eq!(error("rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"), FenError::BadRankLength(6));
+    assert_eq!(error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNX w KQkq - 0 1"), FenError::UnknownPiece('X'));
+    assert_eq!(error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR W KQkq - 0 1"), FenError::BadSideToMove("W".to_owned()));
+    assert_eq!(error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w QKkq - 0 1"), FenError::MalformedCastling("QKkq".to_owned()));
+    assert_eq!(error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KKkq - 0 1"), FenError::MalformedCastling("KKkq".to_owned()));
+    assert_eq!(error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkx - 0 1"), FenError::MalformedCastling("KQkx".to_owned()));
+    assert_eq!(error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBN1 w KQkq - 0 1"), FenError::ImpossibleCastling('K'));
+    assert_eq!(error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e3 0 1"), FenError::ImpossibleEnPassant("e3".to_owned()));
+    assert_eq!(error("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq d3 0 1"), FenError::ImpossibleEnPassant("d3".to_owned()));
+    assert_eq!(error("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e9 0 1"), FenError::ImpossibleEnPassant("e9".to_owned()));
+    assert_eq!(error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 70000 1"), FenError::BadHalfmoveClock("70000".to_owned()));
+    assert_eq!(error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - -1 1"), FenError::BadHalfmoveClock("-1".to_owned()));
+    assert_eq!(error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 0"), FenError::BadMoveNumber("0".to_owned()));
+    assert_eq!(error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 +1"), FenError::BadMoveNumber("+1".to_owned()));
+}
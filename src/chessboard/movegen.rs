@@ -0,0 +1,130 @@
+use crate::bitboard::{self, squares};
+use crate::pieces::{castling_move, Color, PieceType, PROMOTION_PIECES};
+
+use super::{Chessboard, Move, SpecialMove};
+
+fn add_moves(from: u8, targets: u64, moves: &mut Vec<Move>) {
+    let from = bitboard::coordinate(from);
+    for to in bitboard::coordinates(targets) {
+        moves.push(Move::from_coordinates(from, to, None));
+    }
+}
+
+impl Chessboard {
+    /**
+        same moves Piece::append_legal_moves gives for every piece of the player to play, but found with
+        the piece bitboards and precomputed attacks instead of walking the board square by square.
+        The moves are grouped by piece type instead of by square, so they don't come in the same order
+    */
+    pub(super) fn append_pseudo_legal_moves(&self, moves: &mut Vec<Move>) {
+        let color = self.to_play;
+        let own = self.color_occupancy(color);
+        let occupancy = self.occupancy();
+
+        self.append_pawn_moves(moves);
+        for square in squares(self.pieces(PieceType::Rook, color)) {
+            add_moves(square, bitboard::rook_attacks(square, occupancy) & !own, moves);
+        }
+        for square in squares(self.pieces(PieceType::Knight, color)) {
+            add_moves(square, bitboard::knight_attacks(square) & !own, moves);
+        }
+        for square in squares(self.pieces(PieceType::Bishop, color)) {
+            add_moves(square, bitboard::bishop_attacks(square, occupancy) & !own, moves);
+        }
+        for square in squares(self.pieces(PieceType::Queen, color)) {
+            add_moves(square, (bitboard::rook_attacks(square, occupancy) | bitboard::bishop_attacks(square, occupancy)) & !own, moves);
+        }
+        for square in squares(self.pieces(PieceType::King, color)) {
+            add_moves(square, bitboard::king_attacks(square) & !own, moves);
+            for kingside in [true, false] {
+                if let Some(r#move) = castling_move(bitboard::coordinate(square), color, kingside, self) {
+                    moves.push(r#move);
+                }
+            }
+        }
+    }
+
+    fn append_pawn_moves(&self, moves: &mut Vec<Move>) {
+        let color = self.to_play;
+        let (direction, start_y, promotion_y, en_passant_y) = match color {
+            Color::White => (8, 1, 7, 5),
+            Color::Black => (-8, 6, 0, 2)
+        };
+        let enemies = self.color_occupancy(color.opposite());
+        let empty = !self.occupancy();
+        let en_passant = self.en_passant_target()
+            .filter(|target| target.y == en_passant_y)
+            .map_or(0, bitboard::from_coordinate);
+
+        for square in squares(self.pieces(PieceType::Pawn, color)) {
+            let from = bitboard::coordinate(square);
+            // a pawn on its last rank can't move at all
+            if from.y == promotion_y { continue; }
+            let mut add_move = |to: u8, special_move: Option<SpecialMove>| {
+                let to = bitboard::coordinate(to);
+                if to.y == promotion_y {
+                    for piece_type in PROMOTION_PIECES {
+                        moves.push(Move::from_coordinates(from, to, Some(SpecialMove::Promotion(piece_type))));
+                    }
+                } else {
+                    moves.push(Move::from_coordinates(from, to, special_move));
+                }
+            };
+
+            let forward = (square as i8 + direction) as u8;
+            if empty & 1 << forward != 0 {
+                add_move(forward, None);
+                let two_squares_forward = (forward as i8 + direction) as u8;
+                if from.y == start_y && empty & 1 << two_squares_forward != 0 {
+                    add_move(two_squares_forward, Some(SpecialMove::TwoSquareAdvance));
+                }
+            }
+            let attacks = bitboard::pawn_attacks(square, color);
+            for to in squares(attacks & enemies) {
+                add_move(to, None);
+            }
+            for to in squares(attacks & en_passant & empty) {
+                add_move(to, Some(SpecialMove::EnPassant));
+            }
+        }
+    }
+}
+
+// only the sets of moves are compared, the order they are generated in differs
+#[test]
+fn bitboard_move_sets_match_piece_move_sets() {
+    let sorted = |mut moves: Vec<Move>| {
+        moves.sort_by_key(|r#move| format!("{move:?}"));
+        moves
+    };
+
+    for fen in [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        "8/8/8/KPp4r/8/8/8/7k w - c6 0 1"
+    ] {
+        let mut chessboard = Chessboard::from_fen(fen).expect("FEN is invalid");
+        for r#move in chessboard.get_legal_moves() {
+            chessboard.make_move(r#move);
+            for reply in chessboard.get_legal_moves() {
+                chessboard.make_move(reply);
+
+                let mut piece_moves = vec![];
+                for y in 0..8 {
+                    for x in 0..8 {
+                        if let Ok(Some(piece)) = chessboard.get(x, y) {
+                            if piece.color == chessboard.to_play { piece.append_legal_moves(super::Coordinate::new(x, y), &chessboard, &mut piece_moves); }
+                        }
+                    }
+                }
+                assert_eq!(sorted(chessboard.get_pseudo_legal_moves()), sorted(piece_moves), "{fen} {move} {reply}");
+
+                chessboard.unmake_move();
+            }
+            chessboard.unmake_move();
+        }
+    }
+}
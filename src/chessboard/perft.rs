@@ -0,0 +1,75 @@
+use super::{Chessboard, Move};
+
+// https://www.chessprogramming.org/Perft
+impl Chessboard {
+    /** counts the leaf nodes of the tree of legal moves of the given depth */
+    pub fn perft(&mut self, depth: u32) -> u64 {
+        if depth == 0 { return 1; }
+        let moves = self.get_legal_moves();
+        if depth == 1 { return moves.len() as u64; }
+
+        let mut nodes = 0;
+        for r#move in moves {
+            self.make_move(r#move);
+            nodes += self.perft(depth - 1);
+            self.unmake_move();
+        }
+        nodes
+    }
+
+    /** perft split by the first move, handy to find which move a move generation bug hides behind */
+    pub fn divide(&mut self, depth: u32) -> Vec<(Move, u64)> {
+        if depth == 0 { return vec![]; }
+        self.get_legal_moves().into_iter().map(|r#move| {
+            self.make_move(r#move);
+            let nodes = self.perft(depth - 1);
+            self.unmake_move();
+            (r#move, nodes)
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+fn assert_perft(fen: &str, expected: &[u64]) {
+    let mut chessboard = Chessboard::from_fen(fen).expect("FEN is invalid");
+    let original = chessboard.clone();
+    for (depth, nodes) in expected.iter().enumerate() {
+        assert_eq!(chessboard.perft(depth as u32 + 1), *nodes, "{fen} at depth {}", depth + 1);
+    }
+    assert_eq!(chessboard, original);
+}
+
+// positions and node counts from https://www.chessprogramming.org/Perft_Results
+#[test]
+fn perft_start_position() {
+    assert_perft("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", &[20, 400, 8902, 197281]);
+}
+#[test]
+fn perft_kiwipete() {
+    assert_perft("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", &[48, 2039, 97862, 4085603]);
+}
+#[test]
+fn perft_position_3() {
+    assert_perft("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", &[14, 191, 2812, 43238, 674624]);
+}
+#[test]
+fn perft_position_4() {
+    assert_perft("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1", &[6, 264, 9467, 422333]);
+    assert_perft("r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1", &[6, 264, 9467, 422333]);
+}
+#[test]
+fn perft_position_5() {
+    assert_perft("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8", &[44, 1486, 62379, 2103487]);
+}
+#[test]
+fn perft_position_6() {
+    assert_perft("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10", &[46, 2079, 89890, 3894594]);
+}
+#[test]
+fn divide_adds_up_to_perft() {
+    let mut chessboard = Chessboard::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").expect("FEN is invalid");
+    let divide = chessboard.divide(2);
+    assert_eq!(divide.len(), 48);
+    assert_eq!(divide.iter().map(|(_, nodes)| nodes).sum::<u64>(), 2039);
+    assert!(divide.contains(&(Move::from_string("e1g1", Some(super::SpecialMove::Castling)).unwrap(), 43)));
+}
@@ -0,0 +1,208 @@
+use std::fmt::{Display, Formatter, Error};
+
+use crate::pieces::{PieceType, PROMOTION_PIECES};
+
+use super::{Chessboard, Coordinate, GameStatus, Move, SpecialMove};
+
+/** why a string couldn't be turned into a move with Chessboard::parse_san */
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum SanError {
+    /** the string isn't written in Standard Algebraic Notation */
+    InvalidSyntax(String),
+    /** no legal move matches the string */
+    IllegalMove(String),
+    /** more than one legal move matches the string */
+    AmbiguousMove(String)
+}
+
+impl Display for SanError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match self {
+            SanError::InvalidSyntax(san) => write!(f, "'{san}' isn't a move in algebraic notation"),
+            SanError::IllegalMove(san) => write!(f, "'{san}' isn't a legal move"),
+            SanError::AmbiguousMove(san) => write!(f, "'{san}' could be more than one move")
+        }
+    }
+}
+
+impl std::error::Error for SanError {}
+
+// https://en.wikipedia.org/wiki/Algebraic_notation_(chess)
+impl Chessboard {
+    /** writes a legal move in Standard Algebraic Notation, like Nbd7, exd6, O-O-O, e8=Q+ or Qxf7# */
+    pub fn move_to_san(&self, r#move: Move) -> String {
+        let moved = self.get(r#move.from.x, r#move.from.y).expect("invalid move").expect("there was no piece to be moved");
+        let mut san = String::new();
+
+        if r#move.special_move == Some(SpecialMove::Castling) {
+            san.push_str(if r#move.to.x > r#move.from.x { "O-O" } else { "O-O-O" });
+        } else {
+            let is_capture = self.get(r#move.to.x, r#move.to.y) != Ok(None) || r#move.special_move == Some(SpecialMove::EnPassant);
+
+            if moved.piece_type == PieceType::Pawn {
+                if is_capture { san.push((b'a' + r#move.from.x) as char); }
+            } else {
+                san.push(moved.piece_type.to_char().to_ascii_uppercase());
+
+                // only name the starting file and/or rank if another piece of the same kind could go to the same square
+                let others: Vec<Coordinate> = self.get_legal_moves().into_iter()
+                    .filter(|other| other.to == r#move.to && other.from != r#move.from)
+                    .filter(|other| self.get(other.from.x, other.from.y) == Ok(Some(moved)))
+                    .map(|other| other.from)
+                    .collect();
+                if !others.is_empty() {
+                    if others.iter().all(|other| other.x != r#move.from.x) {
+                        san.push((b'a' + r#move.from.x) as char);
+                    } else if others.iter().all(|other| other.y != r#move.from.y) {
+                        san.push((b'1' + r#move.from.y) as char);
+                    } else {
+                        san.push_str(&r#move.from.to_string());
+                    }
+                }
+            }
+
+            if is_capture { san.push('x'); }
+            san.push_str(&r#move.to.to_string());
+
+            if let Some(SpecialMove::Promotion(piece_type)) = r#move.special_move {
+                san.push('=');
+                san.push(piece_type.to_char().to_ascii_uppercase());
+            }
+        }
+
+        let mut after_move = self.clone();
+        after_move.make_move(r#move);
+        if matches!(after_move.game_status(), GameStatus::Checkmate { .. }) {
+            san.push('#');
+        } else if after_move.is_in_check() {
+            san.push('+');
+        }
+        san
+    }
+
+    /**
+        finds the legal move written in Standard Algebraic Notation. Forgiving with the way people actually
+        type: the x can be left out, castling can be written with zeros, promotions can be lowercase and
+        without =, and check, mate, annotation and "e.p." suffixes are ignored
+    */
+    pub fn parse_san(&self, san: &str) -> Result<Move, SanError> {
+        let invalid_syntax = || SanError::InvalidSyntax(san.to_owned());
+
+        let mut text = san.trim();
+        text = text.strip_suffix("e.p.").or_else(|| text.strip_suffix("ep")).unwrap_or(text).trim_end();
+        text = text.trim_end_matches(['+', '#', '!', '?']);
+
+        let legal_moves = self.get_legal_moves();
+        let candidates: Vec<Move> = match text {
+            "O-O" | "0-0" | "O-O-O" | "0-0-0" => {
+                let kingside = text.len() == 3;
+                legal_moves.into_iter()
+                    .filter(|r#move| r#move.special_move == Some(SpecialMove::Castling) && (r#move.to.x > r#move.from.x) == kingside)
+                    .collect()
+            }
+            _ => {
+                let mut chars: Vec<char> = text.chars().filter(|character| !matches!(character, 'x' | ':' | '-')).collect();
+
+                let promotion = match chars.last() {
+                    Some(character) if !character.is_ascii_digit() => {
+                        let piece_type = PieceType::from_char(*character)
+                            .filter(|piece_type| PROMOTION_PIECES.contains(piece_type))
+                            .ok_or_else(invalid_syntax)?;
+                        chars.pop();
+                        if chars.last() == Some(&'=') { chars.pop(); }
+                        Some(piece_type)
+                    }
+                    _ => None
+                };
+
+                if chars.len() < 2 { return Err(invalid_syntax()); }
+                let to = Coordinate::from_string(&chars.split_off(chars.len() - 2).into_iter().collect::<String>()).ok_or_else(invalid_syntax)?;
+
+                let piece_type = match chars.first() {
+                    Some(&character) if character.is_ascii_uppercase() => {
+                        chars.remove(0);
+                        PieceType::from_char(character).ok_or_else(invalid_syntax)?
+                    }
+                    _ => PieceType::Pawn
+                };
+                let (mut from_x, mut from_y) = (None, None);
+                for character in chars {
+                    match character {
+                        'a'..='h' if from_x.is_none() && from_y.is_none() => from_x = Some(character as u8 - b'a'),
+                        '1'..='8' if from_y.is_none() => from_y = Some(character as u8 - b'1'),
+                        _ => return Err(invalid_syntax())
+                    }
+                }
+
+                legal_moves.into_iter().filter(|r#move| {
+                    r#move.to == to &&
+                        r#move.special_move != Some(SpecialMove::Castling) &&
+                        from_x.is_none_or(|x| r#move.from.x == x) &&
+                        from_y.is_none_or(|y| r#move.from.y == y) &&
+                        self.get(r#move.from.x, r#move.from.y).is_ok_and(|piece| piece.is_some_and(|piece| piece.piece_type == piece_type)) &&
+                        match r#move.special_move {
+                            Some(SpecialMove::Promotion(promoted)) => promotion == Some(promoted),
+                            _ => promotion.is_none()
+                        }
+                }).collect()
+            }
+        };
+
+        match candidates[..] {
+            [r#move] => Ok(r#move),
+            [] => Err(SanError::IllegalMove(san.to_owned())),
+            _ => Err(SanError::AmbiguousMove(san.to_owned()))
+        }
+    }
+}
+
+#[test]
+fn move_to_san() {
+    let san = |fen: &str, r#move: &str, special_move: Option<SpecialMove>| {
+        let chessboard = Chessboard::from_fen(fen).expect("FEN is invalid");
+        chessboard.move_to_san(Move::from_string(r#move, special_move).unwrap())
+    };
+
+    assert_eq!(san("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", "e2e4", Some(SpecialMove::TwoSquareAdvance)), "e4");
+    assert_eq!(san("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", "g1f3", None), "Nf3");
+    assert_eq!(san("1n2k3/8/5n2/8/8/8/8/4K3 b - - 0 1", "b8d7", None), "Nbd7");
+    assert_eq!(san("4k3/8/8/8/R7/8/8/R3K3 w - - 0 1", "a1a3", None), "R1a3");
+    assert_eq!(san("8/8/1k6/8/4Q2Q/8/8/K6Q w - - 0 1", "h4e1", None), "Qh4e1");
+    assert_eq!(san("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6", Some(SpecialMove::EnPassant)), "exd6");
+    assert_eq!(san("r3k3/8/8/8/8/8/8/4K3 b q - 0 1", "e8c8", Some(SpecialMove::Castling)), "O-O-O");
+    assert_eq!(san("3k4/4P3/8/8/8/8/8/4K3 w - - 0 1", "e7e8", Some(SpecialMove::Promotion(PieceType::Queen))), "e8=Q+");
+    assert_eq!(san("r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4", "h5f7", None), "Qxf7#");
+}
+
+#[test]
+fn parse_san() {
+    let parse = |fen: &str, san: &str| Chessboard::from_fen(fen).expect("FEN is invalid").parse_san(san);
+    let r#move = |r#move: &str, special_move: Option<SpecialMove>| Ok(Move::from_string(r#move, special_move).unwrap());
+
+    let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+    assert_eq!(parse(start, "e4"), r#move("e2e4", Some(SpecialMove::TwoSquareAdvance)));
+    assert_eq!(parse(start, "Nf3"), r#move("g1f3", None));
+    assert_eq!(parse(start, "Ng1-f3!?"), r#move("g1f3", None));
+    assert_eq!(parse(start, "e5"), Err(SanError::IllegalMove("e5".to_owned())));
+    assert_eq!(parse(start, "Nf3x"), r#move("g1f3", None));
+    assert_eq!(parse(start, "Zf3"), Err(SanError::InvalidSyntax("Zf3".to_owned())));
+    assert_eq!(parse(start, "f9"), Err(SanError::InvalidSyntax("f9".to_owned())));
+    assert_eq!(parse(start, ""), Err(SanError::InvalidSyntax("".to_owned())));
+
+    let knights = "1n2k3/8/5n2/8/8/8/8/4K3 b - - 0 1";
+    assert_eq!(parse(knights, "Nd7"), Err(SanError::AmbiguousMove("Nd7".to_owned())));
+    assert_eq!(parse(knights, "Nbd7"), r#move("b8d7", None));
+    assert_eq!(parse(knights, "N6d7"), r#move("f6d7", None));
+    assert_eq!(parse(knights, "Nf6xd7"), r#move("f6d7", None));
+    assert_eq!(parse(knights, "Ncd7"), Err(SanError::IllegalMove("Ncd7".to_owned())));
+
+    assert_eq!(parse("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "exd6 e.p."), r#move("e5d6", Some(SpecialMove::EnPassant)));
+    assert_eq!(parse("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "ed6"), r#move("e5d6", Some(SpecialMove::EnPassant)));
+    assert_eq!(parse("r3k2r/8/8/8/8/8/8/4K3 b kq - 0 1", "O-O-O"), r#move("e8c8", Some(SpecialMove::Castling)));
+    assert_eq!(parse("r3k2r/8/8/8/8/8/8/4K3 b kq - 0 1", "0-0"), r#move("e8g8", Some(SpecialMove::Castling)));
+    assert_eq!(parse("3k4/4P3/8/8/8/8/8/4K3 w - - 0 1", "e8=Q+"), r#move("e7e8", Some(SpecialMove::Promotion(PieceType::Queen))));
+    assert_eq!(parse("3k4/4P3/8/8/8/8/8/4K3 w - - 0 1", "e8n"), r#move("e7e8", Some(SpecialMove::Promotion(PieceType::Knight))));
+    assert_eq!(parse("3k4/4P3/8/8/8/8/8/4K3 w - - 0 1", "e8"), Err(SanError::IllegalMove("e8".to_owned())));
+    assert_eq!(parse("3k4/4P3/8/8/8/8/8/4K3 w - - 0 1", "e8=K"), Err(SanError::InvalidSyntax("e8=K".to_owned())));
+    assert_eq!(parse("r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4", "Qxf7#"), r#move("h5f7", None));
+}
@@ -0,0 +1,100 @@
+use crate::bitboard;
+use crate::pieces::PieceType;
+
+use super::{Chessboard, Move, SpecialMove};
+
+const PIECE_TYPES: [PieceType; 6] = [PieceType::Pawn, PieceType::Knight, PieceType::Bishop, PieceType::Rook, PieceType::Queen, PieceType::King];
+
+fn see_value(piece_type: PieceType) -> i32 {
+    match piece_type {
+        PieceType::Pawn => 100,
+        PieceType::Knight => 320,
+        PieceType::Bishop => 330,
+        PieceType::Rook => 500,
+        PieceType::Queen => 900,
+        PieceType::King => 20000
+    }
+}
+
+// https://www.chessprogramming.org/SEE_-_The_Swap_Algorithm
+impl Chessboard {
+    /**
+        static exchange evaluation: how much material the player to play wins with the move if both players
+        keep capturing on the destination square with their least valuable piece, and stop as soon as it stops
+        paying off. Pieces behind the ones that capture (x-rays) join in as the line opens. Moves that don't
+        capture anything tell if the piece can safely go to the square
+    */
+    pub fn see(&self, r#move: Move) -> i32 {
+        let (from, to) = (bitboard::square_index(r#move.from), bitboard::square_index(r#move.to));
+        let Ok(Some(moved)) = self.get(r#move.from.x, r#move.from.y) else { return 0 };
+        if r#move.special_move == Some(SpecialMove::Castling) { return 0; }
+
+        let mut occupancy = self.occupancy() & !(1 << from);
+        let mut gains = [0; 32];
+        gains[0] = match r#move.special_move {
+            Some(SpecialMove::EnPassant) => {
+                // the captured pawn isn't on the destination square
+                occupancy &= !(1 << (r#move.to.x + 8 * r#move.from.y));
+                see_value(PieceType::Pawn)
+            }
+            _ => self.get(r#move.to.x, r#move.to.y).ok().flatten().map_or(0, |captured| see_value(captured.piece_type))
+        };
+        // the piece standing on the square, the next one to be captured
+        let mut on_square = see_value(moved.piece_type);
+        if let Some(SpecialMove::Promotion(piece_type)) = r#move.special_move {
+            gains[0] += see_value(piece_type) - see_value(PieceType::Pawn);
+            on_square = see_value(piece_type);
+        }
+
+        let mut color = moved.color.opposite();
+        let mut depth = 0;
+        while depth + 1 < gains.len() {
+            // the pieces already used are out of the occupancy, which also uncovers the sliders behind them
+            let attackers = self.attackers_with_occupancy(to, color, occupancy) & occupancy;
+            let Some((piece_type, attacker)) = PIECE_TYPES.iter()
+                .map(|&piece_type| (piece_type, attackers & self.pieces(piece_type, color)))
+                .find(|(_, bitboard)| *bitboard != 0) else { break };
+            // the king can only take if nothing takes it back
+            if piece_type == PieceType::King && self.attackers_with_occupancy(to, color.opposite(), occupancy & !attacker) & occupancy != 0 { break; }
+
+            depth += 1;
+            gains[depth] = on_square - gains[depth - 1];
+            on_square = see_value(piece_type);
+            occupancy &= !(attacker & attacker.wrapping_neg());
+            color = color.opposite();
+        }
+
+        // each player only keeps capturing if it is better than stopping
+        while depth > 0 {
+            gains[depth - 1] = -(-gains[depth - 1]).max(gains[depth]);
+            depth -= 1;
+        }
+        gains[0]
+    }
+}
+
+#[test]
+fn static_exchange_evaluation() {
+    let see = |fen: &str, r#move: &str, special_move: Option<SpecialMove>| {
+        let chessboard = Chessboard::from_fen(fen).expect("FEN is invalid");
+        let r#move = Move::from_string(r#move, special_move).unwrap();
+        assert_eq!(chessboard.is_legal_move(r#move), Ok(true));
+        chessboard.see(r#move)
+    };
+
+    // a free pawn
+    assert_eq!(see("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1", "e1e5", None), 100);
+    // NxP NxN RxN BxR QxB QxQ, with the rook, queen and black queen joining in through x-rays
+    assert_eq!(see("1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1", "d3e5", None), -220);
+    // the queen goes where a pawn takes it
+    assert_eq!(see("4k3/8/8/2p5/8/2Q5/8/4K3 w - - 0 1", "c3b4", None), -900);
+    assert_eq!(see("4k3/8/8/8/8/2Q5/8/4K3 w - - 0 1", "c3c4", None), 0);
+    // the king takes back, unless the second rook defends the square
+    assert_eq!(see("8/8/4k3/3p4/8/8/8/3RK3 w - - 0 1", "d1d5", None), -400);
+    assert_eq!(see("8/8/4k3/3p4/8/8/3R4/3RK3 w - - 0 1", "d2d5", None), 100);
+    assert_eq!(see("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6", Some(SpecialMove::EnPassant)), 100);
+    assert_eq!(see("4k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7a8", Some(SpecialMove::Promotion(PieceType::Queen))), 800);
+    assert_eq!(see("r3k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b7a8", Some(SpecialMove::Promotion(PieceType::Knight))), 720);
+    assert_eq!(see("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "e1g1", Some(SpecialMove::Castling)), 0);
+    assert_eq!(see("4k3/8/8/8/8/8/8/4K2R w K - 0 1", "h1h8", None), 0);
+}
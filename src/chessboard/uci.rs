@@ -0,0 +1,66 @@
+use std::fmt::{Display, Formatter, Error};
+
+use super::{Chessboard, Move, SpecialMove};
+
+/** why a string couldn't be turned into a move with Chessboard::parse_uci_move */
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum UciMoveError {
+    /** the string isn't a move in long algebraic notation, like e2e4 or e7e8q */
+    InvalidSyntax(String),
+    /** the move isn't legal in the position */
+    IllegalMove(String)
+}
+
+impl Display for UciMoveError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match self {
+            UciMoveError::InvalidSyntax(uci) => write!(f, "'{uci}' isn't a move in long algebraic notation"),
+            UciMoveError::IllegalMove(uci) => write!(f, "'{uci}' isn't a legal move")
+        }
+    }
+}
+
+impl std::error::Error for UciMoveError {}
+
+impl Chessboard {
+    /**
+        finds the legal move written the way UCI does (e2e4, e1g1, e7e8q), figuring out from the position if it is
+        castling, en passant or a two square advance, since the notation doesn't say
+    */
+    pub fn parse_uci_move(&self, uci: &str) -> Result<Move, UciMoveError> {
+        let parsed = Move::from_string(uci, None).ok_or_else(|| UciMoveError::InvalidSyntax(uci.to_owned()))?;
+        let promotion = match parsed.special_move {
+            Some(SpecialMove::Promotion(piece_type)) => Some(piece_type),
+            _ => None
+        };
+
+        self.get_legal_moves().into_iter().find(|r#move| {
+            let move_promotion = match r#move.special_move {
+                Some(SpecialMove::Promotion(piece_type)) => Some(piece_type),
+                _ => None
+            };
+            r#move.from == parsed.from && r#move.to == parsed.to && move_promotion == promotion
+        }).ok_or_else(|| UciMoveError::IllegalMove(uci.to_owned()))
+    }
+}
+
+#[test]
+fn parse_uci_move() {
+    use crate::pieces::PieceType;
+
+    let parse = |fen: &str, uci: &str| Chessboard::from_fen(fen).expect("FEN is invalid").parse_uci_move(uci);
+
+    let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+    assert_eq!(parse(start, "e2e4"), Ok(Move::new(4, 1, 4, 3, Some(SpecialMove::TwoSquareAdvance))));
+    assert_eq!(parse(start, "g1f3"), Ok(Move::new(6, 0, 5, 2, None)));
+    assert_eq!(parse(start, "e2e5"), Err(UciMoveError::IllegalMove("e2e5".to_owned())));
+    assert_eq!(parse(start, "e2e4q"), Err(UciMoveError::IllegalMove("e2e4q".to_owned())));
+    assert_eq!(parse(start, "e2"), Err(UciMoveError::InvalidSyntax("e2".to_owned())));
+    assert_eq!(parse(start, "O-O"), Err(UciMoveError::InvalidSyntax("O-O".to_owned())));
+
+    assert_eq!(parse("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "e1g1"), Ok(Move::new(4, 0, 6, 0, Some(SpecialMove::Castling))));
+    assert_eq!(parse("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1", "e8c8"), Ok(Move::new(4, 7, 2, 7, Some(SpecialMove::Castling))));
+    assert_eq!(parse("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6"), Ok(Move::new(4, 4, 3, 5, Some(SpecialMove::EnPassant))));
+    assert_eq!(parse("3k4/4P3/8/8/8/8/8/4K3 w - - 0 1", "e7e8q"), Ok(Move::new(4, 6, 4, 7, Some(SpecialMove::Promotion(PieceType::Queen)))));
+    assert_eq!(parse("3k4/4P3/8/8/8/8/8/4K3 w - - 0 1", "e7e8"), Err(UciMoveError::IllegalMove("e7e8".to_owned())));
+}
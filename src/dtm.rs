@@ -0,0 +1,680 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter, Error};
+use std::path::Path;
+
+use crate::bitboard;
+use crate::chessboard::{Chessboard, Move, SpecialMove};
+use crate::pieces::{Color, Piece, PieceType};
+use crate::syzygy::material_name;
+
+// distance to mate tables made by retrograde analysis (https://www.chessprogramming.org/Retrograde_Analysis): every
+// position of an ending gets an index, the mates are found first, and the results then spread backwards one ply at
+// a time, through the moves that lead to positions already solved. A position wins in n plies if one of its moves
+// leads to a loss in n - 1, and loses in n plies once all of its moves lead to wins, the slowest one in n - 1. Moves
+// that capture or promote leave the ending, so they are looked up in the tables of the smaller endings, which are
+// made first. Whatever is left at the end is a draw. The 50 move rule is ignored.
+// Symmetric positions share an index: without pawns the first king is moved to the a1-d1-d4 triangle, with pawns to
+// the a-d files. One byte per index holds the result, and the files only keep the valid positions, with the long runs
+// of the same result run-length encoded
+
+/** the most pieces, kings included, the tables can be made with */
+pub const MAX_PIECES: usize = 4;
+
+/** the value of a position which is drawn, the others hold the plies to mate plus one */
+const DRAW: u8 = 0;
+/** the value of the indices that aren't positions: pieces on the same square, the king that can be taken... */
+const INVALID: u8 = 255;
+/** the value of the positions that aren't solved yet, while making the table */
+const UNKNOWN: u8 = 254;
+/** the counter of a position once the positions it can be reached from know its result */
+const PROPAGATED: u8 = u8::MAX;
+/** the byte starting a run of the same value in the files, where INVALID is never written */
+const RUN: u8 = INVALID;
+/** the shortest run written as one, the shorter ones take less space as they are */
+const MIN_RUN: usize = 4;
+const MAGIC: [u8; 4] = *b"RCDT";
+const PIECE_ORDER: &str = "KQRBNP";
+/** a1, b1, c1, d1, b2, c2, d2, c3, d3 and d4 */
+const TRIANGLE: [u8; 10] = [0, 1, 2, 3, 9, 10, 11, 18, 19, 27];
+
+/** the result of a position for the player to play, with the plies until mate */
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Dtm {
+    /** always an odd number of plies, Win(1) mates right away */
+    Win(u16),
+    Draw,
+    /** always an even number of plies, Loss(0) is being mated */
+    Loss(u16)
+}
+
+impl Dtm {
+    fn from_value(value: u8) -> Option<Self> {
+        match value {
+            INVALID | UNKNOWN => None,
+            DRAW => Some(Dtm::Draw),
+            _ => {
+                let plies = value as u16 - 1;
+                Some(if plies.is_multiple_of(2) { Dtm::Loss(plies) } else { Dtm::Win(plies) })
+            }
+        }
+    }
+    fn to_value(self) -> u8 {
+        match self {
+            Dtm::Draw => DRAW,
+            Dtm::Win(plies) | Dtm::Loss(plies) => {
+                assert!(plies < UNKNOWN as u16 - 1, "no ending of {MAX_PIECES} pieces takes that long to mate");
+                plies as u8 + 1
+            }
+        }
+    }
+    /** the result for the player who made the move leading to the position */
+    pub fn before_move(self) -> Self {
+        match self {
+            Dtm::Win(plies) => Dtm::Loss(plies + 1),
+            Dtm::Draw => Dtm::Draw,
+            Dtm::Loss(plies) => Dtm::Win(plies + 1)
+        }
+    }
+    /** the higher the better for the player to play: quick wins, then draws, then slow losses */
+    fn score(self) -> i32 {
+        match self {
+            Dtm::Win(plies) => i32::MAX - plies as i32,
+            Dtm::Draw => 0,
+            Dtm::Loss(plies) => i32::MIN + 1 + plies as i32
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum DtmError {
+    /** the name isn't made of the pieces of both sides, each starting with its king, like KRvKP or KRKP */
+    InvalidName(String),
+    /** how many pieces the ending has, which is more than MAX_PIECES */
+    TooManyPieces(usize),
+    Io(std::io::ErrorKind),
+    /** the file isn't a table, or it is cut short */
+    InvalidFile
+}
+
+impl Display for DtmError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match self {
+            DtmError::InvalidName(name) => write!(f, "'{name}' isn't the name of an ending, like KRvKP"),
+            DtmError::TooManyPieces(count) => write!(f, "tables can't have {count} pieces, {MAX_PIECES} at most"),
+            DtmError::Io(kind) => write!(f, "couldn't access the table file: {kind}"),
+            DtmError::InvalidFile => write!(f, "the file isn't a valid table")
+        }
+    }
+}
+
+impl std::error::Error for DtmError {}
+
+/** the pieces of both sides of the name, each in KQRBNP order */
+fn parse_name(name: &str) -> Result<(String, String), DtmError> {
+    let invalid = || DtmError::InvalidName(name.to_owned());
+    let (white, black) = match name.split_once('v') {
+        Some(sides) => sides,
+        // without the v, the second side starts at the second king
+        None => name.split_at(name.get(1..).and_then(|rest| rest.find('K')).ok_or_else(invalid)? + 1)
+    };
+    let side = |side: &str| {
+        let valid = side.starts_with('K') && side.matches('K').count() == 1 && side.chars().all(|character| PIECE_ORDER.contains(character));
+        if !valid { return Err(invalid()); }
+        let mut pieces: Vec<char> = side.chars().collect();
+        pieces.sort_by_key(|&character| PIECE_ORDER.find(character));
+        Ok(pieces.into_iter().collect::<String>())
+    };
+    Ok((side(white)?, side(black)?))
+}
+
+/** the pieces of the color as they are written in the names, like KRP */
+fn side_name(chessboard: &Chessboard, color: Color) -> String {
+    PIECE_ORDER.chars().map(|character| {
+        let piece_type = PieceType::from_char(character).expect("all of them are pieces");
+        character.to_string().repeat(chessboard.pieces(piece_type, color).count_ones() as usize)
+    }).collect()
+}
+
+/** the squares of the symmetric position: 1 mirrors the files, 2 the ranks and 4 the a1-h8 diagonal */
+fn transform(square: u8, symmetry: usize) -> u8 {
+    let mut square = if symmetry & 4 != 0 { (square >> 3) | ((square & 7) << 3) } else { square };
+    if symmetry & 1 != 0 { square ^= 7; }
+    if symmetry & 2 != 0 { square ^= 56; }
+    square
+}
+
+/**
+    how the positions of an ending are indexed: by the player to play, the en passant right, the square of the
+    first king within its region, and the squares of the other pieces
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Layout {
+    /** the pieces of the first side of the name are white, the first one being its king */
+    pieces: Vec<Piece>,
+    /** 2 when both sides have a pawn, one of them may have just moved two squares next to the other */
+    en_passant_states: usize,
+    /** the index of the square among the ones the first king is moved to, or u8::MAX */
+    region: [u8; 64],
+    region_size: usize,
+    /** pawns only move forward, so positions with pawns can't be turned around but only mirrored */
+    symmetries: usize,
+    size: usize
+}
+
+impl Layout {
+    fn new(white: &str, black: &str) -> Self {
+        let pieces: Vec<Piece> = white.chars().map(|character| (character, Color::White))
+            .chain(black.chars().map(|character| (character, Color::Black)))
+            .map(|(character, color)| Piece::new(color, PieceType::from_char(character).expect("the name was checked")))
+            .collect();
+        let has_pawns = pieces.iter().any(|piece| piece.piece_type == PieceType::Pawn);
+        let both_have_pawns = white.contains('P') && black.contains('P');
+
+        let mut region = [u8::MAX; 64];
+        let squares: Vec<u8> = if has_pawns { (0..64).filter(|square| square & 7 < 4).collect() } else { TRIANGLE.to_vec() };
+        for (index, &square) in squares.iter().enumerate() { region[square as usize] = index as u8; }
+
+        let en_passant_states = if both_have_pawns { 2 } else { 1 };
+        let size = 2 * en_passant_states * squares.len() * 64usize.pow(pieces.len() as u32 - 1);
+        Layout { pieces, en_passant_states, region, region_size: squares.len(), symmetries: if has_pawns { 2 } else { 8 }, size }
+    }
+
+    /** the index of the position, the smallest of the symmetric ones, None if the first king can't be moved to its region */
+    fn index(&self, to_play: Color, en_passant: bool, squares: &[u8]) -> Option<usize> {
+        let mut best = None;
+        for symmetry in 0..self.symmetries {
+            let mut transformed = [0; MAX_PIECES];
+            for (transformed, &square) in transformed.iter_mut().zip(squares) { *transformed = transform(square, symmetry); }
+            let region = self.region[transformed[0] as usize];
+            if region == u8::MAX { continue; }
+            // the same pieces can be swapped, so they are kept in order
+            for _ in 0..self.pieces.len() {
+                for i in 1..self.pieces.len() {
+                    if self.pieces[i] == self.pieces[i - 1] && transformed[i] < transformed[i - 1] { transformed.swap(i, i - 1); }
+                }
+            }
+
+            let mut index = ((to_play as usize) * self.en_passant_states + en_passant as usize) * self.region_size + region as usize;
+            for &square in &transformed[1..self.pieces.len()] { index = index * 64 + square as usize; }
+            best = Some(best.map_or(index, |best: usize| best.min(index)));
+        }
+        best
+    }
+
+    fn decode(&self, mut index: usize) -> (Color, bool, [u8; MAX_PIECES]) {
+        let mut squares = [0; MAX_PIECES];
+        for square in squares[1..self.pieces.len()].iter_mut().rev() {
+            *square = (index % 64) as u8;
+            index /= 64;
+        }
+        let region = index % self.region_size;
+        squares[0] = self.region.iter().position(|&square| square as usize == region).expect("every region index has a square") as u8;
+        index /= self.region_size;
+        let en_passant = index % self.en_passant_states == 1;
+        let to_play = if index / self.en_passant_states == 0 { Color::White } else { Color::Black };
+        (to_play, en_passant, squares)
+    }
+
+    /** the squares of the pieces of the position, which has the material of the layout */
+    fn squares(&self, chessboard: &Chessboard, swap_colors: bool) -> [u8; MAX_PIECES] {
+        let mut squares = [0; MAX_PIECES];
+        let mut taken = 0;
+        for (square, piece) in squares.iter_mut().zip(&self.pieces) {
+            let color = if swap_colors { piece.color.opposite() } else { piece.color };
+            let pieces = chessboard.pieces(piece.piece_type, color) & !taken;
+            *square = pieces.trailing_zeros() as u8;
+            taken |= 1 << *square;
+            if swap_colors { *square ^= 56; }
+        }
+        squares
+    }
+
+    /** the position at the index, None if there isn't one or if it is stored at another index */
+    fn position(&self, index: usize) -> Option<Chessboard> {
+        let (to_play, en_passant, squares) = self.decode(index);
+        let squares = &squares[..self.pieces.len()];
+        let occupancy = squares.iter().fold(0u64, |occupancy, &square| occupancy | 1 << square);
+        if occupancy.count_ones() as usize != squares.len() { return None; }
+        let pawn_on_last_rank = self.pieces.iter().zip(squares).any(|(piece, &square)| piece.piece_type == PieceType::Pawn && matches!(square >> 3, 0 | 7));
+        if pawn_on_last_rank || self.index(to_play, en_passant, squares) != Some(index) { return None; }
+
+        let mut chessboard = Chessboard::default();
+        for (piece, &square) in self.pieces.iter().zip(squares) { chessboard.set_code(square & 7, square >> 3, piece.to_code()); }
+        chessboard.to_play = to_play;
+        if en_passant {
+            // the pawn of the other player just moved two squares, through squares that have to be empty
+            let pawn = self.pieces.iter().zip(squares).find(|(piece, _)| **piece == Piece::new(to_play.opposite(), PieceType::Pawn)).map(|(_, &square)| square)?;
+            let (from_y, to_y) = if to_play == Color::Black { (1, 3) } else { (6, 4) };
+            let passed = (1u64 << (pawn & 7)) << (8 * from_y) | (1u64 << (pawn & 7)) << (8 * (from_y + to_y) / 2);
+            if pawn >> 3 != to_y || occupancy & passed != 0 { return None; }
+            chessboard.last_move = Some(Move::new(pawn & 7, from_y, pawn & 7, to_y, Some(SpecialMove::TwoSquareAdvance)));
+            chessboard.en_passant_file()?;
+        }
+        let king = chessboard.king_square(to_play.opposite())?;
+        if chessboard.is_square_attacked(bitboard::coordinate(king), to_play) { return None; }
+        Some(chessboard)
+    }
+
+    /**
+        the indices of the positions with a move leading to the one at the index without capturing or promoting,
+        found by taking back the moves of the player who isn't to play. Some of them may not be valid positions
+    */
+    fn predecessors(&self, index: usize, predecessors: &mut Vec<usize>) {
+        predecessors.clear();
+        let (to_play, en_passant, squares) = self.decode(index);
+        let mover = to_play.opposite();
+        let occupancy = squares[..self.pieces.len()].iter().fold(0u64, |occupancy, &square| occupancy | 1 << square);
+        let pawns_to_play = self.pieces.iter().zip(squares).filter(|(piece, _)| **piece == Piece::new(to_play, PieceType::Pawn)).fold(0u64, |pawns, (_, square)| pawns | 1 << square);
+
+        for (slot, piece) in self.pieces.iter().enumerate().filter(|(_, piece)| piece.color == mover) {
+            let square = squares[slot];
+            let mut origins = match piece.piece_type {
+                PieceType::King => bitboard::king_attacks(square),
+                PieceType::Knight => bitboard::knight_attacks(square),
+                PieceType::Bishop => bitboard::bishop_attacks(square, occupancy),
+                PieceType::Rook => bitboard::rook_attacks(square, occupancy),
+                PieceType::Queen => bitboard::bishop_attacks(square, occupancy) | bitboard::rook_attacks(square, occupancy),
+                PieceType::Pawn => 0
+            } & !occupancy;
+            if piece.piece_type == PieceType::Pawn {
+                let forward: i8 = if mover == Color::White { 8 } else { -8 };
+                let behind = (square as i8 - forward) as u8;
+                // pawns never stand on the first rank, and the ones on the last one were promoted
+                if (1..7).contains(&(behind >> 3)) && occupancy & 1 << behind == 0 {
+                    let two_behind = (behind as i8 - forward) as u8;
+                    let pushed_rank = if mover == Color::White { 3 } else { 4 };
+                    // moving two squares next to a pawn of the other player gives it the en passant right, which
+                    // no other move does
+                    let gives_en_passant = bitboard::pawn_attacks(behind, mover) & pawns_to_play != 0;
+                    if !en_passant { origins |= 1 << behind; }
+                    if square >> 3 == pushed_rank && occupancy & 1 << two_behind == 0 && gives_en_passant == en_passant {
+                        origins |= 1 << two_behind;
+                    }
+                }
+            } else if en_passant {
+                origins = 0;
+            }
+
+            for origin in bitboard::squares(origins) {
+                let mut origin_squares = squares;
+                origin_squares[slot] = origin;
+                for en_passant in 0..self.en_passant_states {
+                    predecessors.extend(self.index(mover, en_passant == 1, &origin_squares[..self.pieces.len()]));
+                }
+            }
+        }
+        predecessors.sort_unstable();
+        predecessors.dedup();
+    }
+}
+
+/** the distance to mate of every position of an ending */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DtmTable {
+    /** like KRvKP, the first side being white in the table */
+    name: String,
+    sides: (String, String),
+    layout: Layout,
+    values: Vec<u8>
+}
+
+impl DtmTable {
+    /** solves the ending, the tables of the endings its captures and promotions lead to have to be in tables */
+    fn generate(name: &str, tables: &DtmTables) -> Self {
+        let (white, black) = parse_name(name).expect("the name was checked");
+        let layout = Layout::new(&white, &black);
+        let mut values = vec![INVALID; layout.size];
+        // how many of the positions a move leads to aren't known to be lost by the player to play yet
+        let mut counts = vec![0u8; layout.size];
+        // the best result of the moves capturing or promoting, UNKNOWN if there aren't any
+        let mut exits = vec![UNKNOWN; layout.size];
+        // the positions that are solved, or that win through a capture or promotion, by plies to mate
+        let mut pending: Vec<Vec<u32>> = vec![vec![]; UNKNOWN as usize];
+
+        let mut successors = vec![];
+        for index in 0..layout.size {
+            let Some(mut chessboard) = layout.position(index) else { continue };
+            let moves = chessboard.get_legal_moves();
+            let mut best_exit: Option<Dtm> = None;
+            successors.clear();
+            for &r#move in &moves {
+                let (_, captured) = chessboard.make_move(r#move);
+                if captured.is_some() || matches!(r#move.special_move, Some(SpecialMove::Promotion(_))) {
+                    let dtm = tables.probe(&chessboard).expect("the smaller endings are solved first").before_move();
+                    if best_exit.is_none_or(|best| dtm.score() > best.score()) { best_exit = Some(dtm); }
+                } else {
+                    let en_passant = layout.en_passant_states == 2 && chessboard.en_passant_file().is_some();
+                    successors.extend(layout.index(chessboard.to_play, en_passant, &layout.squares(&chessboard, false)[..layout.pieces.len()]));
+                }
+                chessboard.unmake_move();
+            }
+            successors.sort_unstable();
+            successors.dedup();
+
+            counts[index] = successors.len() as u8;
+            exits[index] = best_exit.map_or(UNKNOWN, Dtm::to_value);
+            let dtm = if moves.is_empty() {
+                Some(if chessboard.is_in_check() { Dtm::Loss(0) } else { Dtm::Draw })
+            } else if successors.is_empty() {
+                best_exit
+            } else {
+                if let Some(Dtm::Win(plies)) = best_exit { pending[plies as usize].push(index as u32); }
+                None
+            };
+            values[index] = dtm.map_or(UNKNOWN, Dtm::to_value);
+            if let Some(Dtm::Win(plies) | Dtm::Loss(plies)) = dtm { pending[plies as usize].push(index as u32); }
+        }
+
+        let mut predecessors = vec![];
+        for plies in 0..pending.len() {
+            for index in std::mem::take(&mut pending[plies]) {
+                let index = index as usize;
+                // still unknown, the position was waiting to win through a capture or promotion
+                if values[index] == UNKNOWN { values[index] = Dtm::Win(plies as u16).to_value(); }
+                // a position can be waiting while it wins faster some other way, or be there twice
+                let dtm = Dtm::from_value(values[index]).expect("the position is solved");
+                if counts[index] == PROPAGATED || !matches!(dtm, Dtm::Win(p) | Dtm::Loss(p) if p as usize == plies) { continue; }
+                counts[index] = PROPAGATED;
+
+                layout.predecessors(index, &mut predecessors);
+                for &predecessor in &predecessors {
+                    if values[predecessor] != UNKNOWN { continue; }
+                    if let Dtm::Loss(_) = dtm {
+                        values[predecessor] = Dtm::Win(plies as u16 + 1).to_value();
+                        pending[plies + 1].push(predecessor as u32);
+                        continue;
+                    }
+
+                    counts[predecessor] -= 1;
+                    if counts[predecessor] > 0 { continue; }
+                    // every move staying in the ending loses, so it depends on the captures and promotions
+                    match Dtm::from_value(exits[predecessor]) {
+                        Some(Dtm::Win(_)) => (),
+                        Some(Dtm::Draw) => values[predecessor] = DRAW,
+                        exit => {
+                            let plies = match exit { Some(Dtm::Loss(exit_plies)) => exit_plies.max(plies as u16 + 1), _ => plies as u16 + 1 };
+                            values[predecessor] = Dtm::Loss(plies).to_value();
+                            pending[plies as usize].push(predecessor as u32);
+                        }
+                    }
+                }
+            }
+        }
+
+        for value in values.iter_mut().filter(|value| **value == UNKNOWN) { *value = DRAW; }
+        DtmTable { name: name.to_owned(), sides: (white, black), layout, values }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /** the result of the position, None if it doesn't have the material of the table or has castling rights */
+    pub fn probe(&self, chessboard: &Chessboard) -> Option<Dtm> {
+        if chessboard.castling.iter().any(|&can_castle| can_castle) { return None; }
+        let sides = (side_name(chessboard, Color::White), side_name(chessboard, Color::Black));
+        // the table has the first side of the name as white, so positions where black has it are turned around
+        let swap_colors = if sides == self.sides {
+            false
+        } else if (&sides.1, &sides.0) == (&self.sides.0, &self.sides.1) {
+            true
+        } else {
+            return None;
+        };
+
+        let to_play = if swap_colors { chessboard.to_play.opposite() } else { chessboard.to_play };
+        let en_passant = self.layout.en_passant_states == 2 && chessboard.en_passant_file().is_some();
+        let squares = self.layout.squares(chessboard, swap_colors);
+        let index = self.layout.index(to_play, en_passant, &squares[..self.layout.pieces.len()])?;
+        Dtm::from_value(self.values[index])
+    }
+
+    /**
+        the contents of the file: the magic, the name, and the values of the valid positions, where the runs of at
+        least MIN_RUN times the same value are written as RUN, the value and the length
+    */
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(self.name.len() as u8);
+        bytes.extend(self.name.as_bytes());
+        let values: Vec<u8> = self.values.iter().copied().filter(|&value| value != INVALID).collect();
+        for run in values.chunk_by(|a, b| a == b) {
+            if run.len() < MIN_RUN {
+                bytes.extend(run);
+                continue;
+            }
+            bytes.extend([RUN, run[0]]);
+            // the length of the run, 7 bits at a time with the highest bit telling if more follow
+            let mut length = run.len();
+            while length >= 0x80 {
+                bytes.push((length as u8 & 0x7F) | 0x80);
+                length >>= 7;
+            }
+            bytes.push(length as u8);
+        }
+        bytes
+    }
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DtmError> {
+        let (magic, bytes) = bytes.split_at_checked(MAGIC.len()).ok_or(DtmError::InvalidFile)?;
+        let (&name_length, bytes) = bytes.split_first().filter(|_| magic == MAGIC).ok_or(DtmError::InvalidFile)?;
+        let (name, mut bytes) = bytes.split_at_checked(name_length as usize).ok_or(DtmError::InvalidFile)?;
+        let name = std::str::from_utf8(name).map_err(|_| DtmError::InvalidFile)?;
+        let (white, black) = parse_name(name).map_err(|_| DtmError::InvalidFile)?;
+        if name != material_name(&white, &black) || white.len() + black.len() > MAX_PIECES { return Err(DtmError::InvalidFile); }
+        let layout = Layout::new(&white, &black);
+
+        let mut stored = Vec::with_capacity(layout.size);
+        while let Some((&value, rest)) = bytes.split_first() {
+            bytes = rest;
+            if value != RUN {
+                if value == UNKNOWN || stored.len() == layout.size { return Err(DtmError::InvalidFile); }
+                stored.push(value);
+                continue;
+            }
+            let (&value, rest) = bytes.split_first().filter(|(&value, _)| value < UNKNOWN).ok_or(DtmError::InvalidFile)?;
+            bytes = rest;
+            let mut length = 0;
+            let mut shift = 0;
+            loop {
+                let (&byte, rest) = bytes.split_first().ok_or(DtmError::InvalidFile)?;
+                bytes = rest;
+                length |= ((byte & 0x7F) as usize) << shift;
+                if byte & 0x80 == 0 { break; }
+                shift += 7;
+                // no table is long enough to need more than 4 bytes
+                if shift > 21 { return Err(DtmError::InvalidFile); }
+            }
+            if length > layout.size - stored.len() { return Err(DtmError::InvalidFile); }
+            stored.resize(stored.len() + length, value);
+        }
+        // the invalid positions aren't in the file, they are found again from the layout
+        let mut stored = stored.into_iter();
+        let values: Option<Vec<u8>> = (0..layout.size)
+            .map(|index| if layout.position(index).is_some() { stored.next() } else { Some(INVALID) })
+            .collect();
+        let values = values.ok_or(DtmError::InvalidFile)?;
+        if stored.next().is_some() { return Err(DtmError::InvalidFile); }
+        Ok(DtmTable { name: name.to_owned(), sides: (white, black), layout, values })
+    }
+
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, DtmError> {
+        let bytes = std::fs::read(path).map_err(|error| DtmError::Io(error.kind()))?;
+        Self::from_bytes(&bytes)
+    }
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), DtmError> {
+        std::fs::write(path, self.to_bytes()).map_err(|error| DtmError::Io(error.kind()))
+    }
+}
+
+/** the tables of several endings, probed by the material of the position */
+#[derive(Debug, Default, Clone)]
+pub struct DtmTables {
+    /** indexed by name */
+    tables: HashMap<String, DtmTable>
+}
+
+impl DtmTables {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /**
+        makes the table of the ending, named like KRvKP or KRKP, along with the ones of the endings its captures
+        and promotions lead to, unless they are already there
+    */
+    pub fn generate(&mut self, name: &str) -> Result<(), DtmError> {
+        let (white, black) = parse_name(name)?;
+        let piece_count = white.len() + black.len();
+        if piece_count > MAX_PIECES { return Err(DtmError::TooManyPieces(piece_count)); }
+        let name = material_name(&white, &black);
+        // kings alone are always a draw, there's no need for a table
+        if piece_count == 2 || self.tables.contains_key(&name) { return Ok(()); }
+
+        for (side, other) in [(&white, &black), (&black, &white)] {
+            for (i, piece) in side.char_indices().skip(1) {
+                let mut captured = side.clone();
+                captured.remove(i);
+                self.generate(&format!("{captured}v{other}"))?;
+                if piece != 'P' { continue; }
+                for promoted in ['Q', 'R', 'B', 'N'] {
+                    self.generate(&format!("{}{promoted}v{other}", captured))?;
+                }
+            }
+        }
+        let table = DtmTable::generate(&name, self);
+        self.tables.insert(name, table);
+        Ok(())
+    }
+
+    /** adds a table read from a file */
+    pub fn insert(&mut self, table: DtmTable) {
+        self.tables.insert(table.name.clone(), table);
+    }
+    /** the table of the ending, named like KRvKP or KRKP */
+    pub fn get(&self, name: &str) -> Option<&DtmTable> {
+        let (white, black) = parse_name(name).ok()?;
+        self.tables.get(&material_name(&white, &black))
+    }
+
+    /** the result of the position, None if its table isn't there */
+    pub fn probe(&self, chessboard: &Chessboard) -> Option<Dtm> {
+        if chessboard.castling.iter().any(|&can_castle| can_castle) { return None; }
+        if chessboard.occupancy().count_ones() == 2 { return Some(Dtm::Draw); }
+        let name = material_name(&side_name(chessboard, Color::White), &side_name(chessboard, Color::Black));
+        self.tables.get(&name)?.probe(chessboard)
+    }
+
+    /** the move winning the fastest, or drawing, or losing the slowest. None without legal moves or tables */
+    pub fn best_move(&self, chessboard: &Chessboard) -> Option<Move> {
+        let mut chessboard = chessboard.clone();
+        let mut best: Option<(Move, Dtm)> = None;
+        for r#move in chessboard.get_legal_moves() {
+            chessboard.make_move(r#move);
+            let dtm = self.probe(&chessboard).map(Dtm::before_move);
+            chessboard.unmake_move();
+
+            let dtm = dtm?;
+            if best.is_none_or(|(_, best)| dtm.score() > best.score()) { best = Some((r#move, dtm)); }
+        }
+        best.map(|(r#move, _)| r#move)
+    }
+}
+
+#[test]
+fn names() {
+    assert_eq!(parse_name("KRvKP"), Ok(("KR".to_owned(), "KP".to_owned())));
+    assert_eq!(parse_name("KRKP"), Ok(("KR".to_owned(), "KP".to_owned())));
+    assert_eq!(parse_name("KNKB"), Ok(("KN".to_owned(), "KB".to_owned())));
+    assert_eq!(parse_name("KNBK"), Ok(("KBN".to_owned(), "K".to_owned())));
+    assert_eq!(parse_name("KK"), Ok(("K".to_owned(), "K".to_owned())));
+    for name in ["KQ", "QKvK", "KQvKvK", "KXvK", "KqvK", ""] {
+        assert_eq!(parse_name(name), Err(DtmError::InvalidName(name.to_owned())));
+    }
+    assert_eq!(DtmTables::new().generate("KQRvKR"), Err(DtmError::TooManyPieces(5)));
+}
+
+/** the plies of the longest mate of the table */
+#[cfg(test)]
+fn longest_win(table: &DtmTable) -> Option<u16> {
+    table.values.iter().filter_map(|&value| match Dtm::from_value(value) { Some(Dtm::Win(plies)) => Some(plies), _ => None }).max()
+}
+
+#[test]
+fn generate_table() {
+    let mut tables = DtmTables::new();
+    tables.generate("KQK").unwrap();
+    let table = tables.get("KQvK").unwrap();
+    // the longest mate with a queen takes 10 moves
+    assert_eq!(longest_win(table), Some(19));
+
+    let chessboard = |fen| Chessboard::from_fen(fen).unwrap();
+    let probe = |fen| tables.probe(&chessboard(fen));
+    assert_eq!(probe("k7/8/1K6/8/8/8/8/6Q1 w - - 0 1"), Some(Dtm::Win(1)));
+    assert_eq!(probe("k5Q1/8/1K6/8/8/8/8/8 b - - 0 1"), Some(Dtm::Loss(0)));
+    // with the colors the other way around
+    assert_eq!(probe("6q1/8/8/8/8/1k6/8/K7 b - - 0 1"), Some(Dtm::Win(1)));
+    // stalemate, and the queen that can be taken
+    assert_eq!(probe("k7/2Q5/1K6/8/8/8/8/8 b - - 0 1"), Some(Dtm::Draw));
+    assert_eq!(probe("8/8/8/8/8/8/1kQ5/7K b - - 0 1"), Some(Dtm::Draw));
+    assert_eq!(probe("4k3/8/8/8/8/8/8/4K3 w - - 0 1"), Some(Dtm::Draw));
+    // positions that can't happen, or that aren't in the tables
+    assert_eq!(probe("k7/8/1K6/8/8/8/8/7Q w - - 0 1"), None);
+    assert_eq!(probe("k7/8/1K6/8/8/8/8/7R w - - 0 1"), None);
+    assert_eq!(table.probe(&chessboard("4k3/8/8/8/8/8/8/4K3 w - - 0 1")), None);
+
+    // the best move mates the fastest, one ply closer at every move
+    let mut position = chessboard("8/8/8/4k3/8/8/8/3QK3 w - - 0 1");
+    let Some(Dtm::Win(plies)) = tables.probe(&position) else { panic!("the queen wins") };
+    for ply in (0..plies).rev() {
+        position.make_move(tables.best_move(&position).unwrap());
+        let expected = if ply % 2 == 0 { Dtm::Loss(ply) } else { Dtm::Win(ply) };
+        assert_eq!(tables.probe(&position), Some(expected));
+    }
+    assert_eq!(tables.best_move(&position), None);
+
+    // the file reads back the same, with less than half a byte per index
+    let bytes = table.to_bytes();
+    assert!(bytes.len() * 2 < table.values.len());
+    assert_eq!(DtmTable::from_bytes(&bytes).as_ref(), Ok(table));
+    assert_eq!(DtmTable::from_bytes(&bytes[..bytes.len() - 1]), Err(DtmError::InvalidFile));
+    assert_eq!(DtmTable::from_bytes(&bytes[1..]), Err(DtmError::InvalidFile));
+    let mut loaded = DtmTables::new();
+    loaded.insert(DtmTable::from_bytes(&bytes).unwrap());
+    assert_eq!(loaded.probe(&chessboard("k7/8/1K6/8/8/8/8/6Q1 w - - 0 1")), Some(Dtm::Win(1)));
+}
+
+// the endings of 4 pieces take minutes to make, so this only runs with: cargo test --release big_tables -- --ignored
+#[test]
+#[ignore]
+fn big_tables() {
+    let mut tables = DtmTables::new();
+    // the longest mates in 28, 33, 35 and 40 moves
+    for (name, plies) in [("KPvK", 55), ("KBNvK", 65), ("KQvKR", 69), ("KRvKN", 79)] {
+        tables.generate(name).unwrap();
+        assert_eq!(longest_win(tables.get(name).unwrap()), Some(plies), "{name}");
+    }
+
+    // with a pawn each, some positions can be taken en passant, and the pawns promote into the other tables
+    tables.generate("KPvKP").unwrap();
+    let table = tables.get("KPvKP").unwrap();
+    let mut en_passant = 0;
+    for index in (0..table.layout.size).step_by(7) {
+        let Some(mut chessboard) = table.layout.position(index) else { continue };
+        en_passant += chessboard.en_passant_file().is_some() as usize;
+        // every position gets the best result of its moves
+        let mut best: Option<Dtm> = None;
+        for r#move in chessboard.get_legal_moves() {
+            chessboard.make_move(r#move);
+            let dtm = tables.probe(&chessboard).unwrap().before_move();
+            chessboard.unmake_move();
+            if best.is_none_or(|best| dtm.score() > best.score()) { best = Some(dtm); }
+        }
+        let expected = best.unwrap_or(if chessboard.is_in_check() { Dtm::Loss(0) } else { Dtm::Draw });
+        assert_eq!(table.probe(&chessboard), Some(expected), "{}", chessboard.to_fen());
+    }
+    assert!(en_passant > 0);
+
+    let bytes = table.to_bytes();
+    assert!(bytes.len() * 2 < table.values.len());
+    assert_eq!(DtmTable::from_bytes(&bytes).as_ref(), Ok(table));
+}
@@ -0,0 +1,428 @@
+use std::fmt::{Display, Formatter, Error};
+use std::ops::{Add, AddAssign, Neg, Sub};
+
+use crate::bitboard;
+use crate::chessboard::Chessboard;
+use crate::pieces::{Color, PieceType};
+
+// a tapered evaluation (https://www.chessprogramming.org/Tapered_Eval): every term has a middlegame and an endgame
+// value, and the two are blended depending on how much material is left on the board
+
+/** a middlegame and an endgame value, in centipawns */
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TaperedScore {
+    pub middlegame: i32,
+    pub endgame: i32
+}
+impl TaperedScore {
+    pub const fn new(middlegame: i32, endgame: i32) -> Self {
+        TaperedScore { middlegame, endgame }
+    }
+    /** the value at the phase, which goes from 0 (only kings and pawns) to MAX_PHASE (all the pieces) */
+    pub fn blend(self, phase: i32) -> i32 {
+        (self.middlegame * phase + self.endgame * (MAX_PHASE - phase)) / MAX_PHASE
+    }
+}
+impl Add for TaperedScore {
+    type Output = Self;
+    fn add(self, other: Self) -> Self {
+        TaperedScore::new(self.middlegame + other.middlegame, self.endgame + other.endgame)
+    }
+}
+impl AddAssign for TaperedScore {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+impl Sub for TaperedScore {
+    type Output = Self;
+    fn sub(self, other: Self) -> Self {
+        TaperedScore::new(self.middlegame - other.middlegame, self.endgame - other.endgame)
+    }
+}
+impl Neg for TaperedScore {
+    type Output = Self;
+    fn neg(self) -> Self {
+        TaperedScore::new(-self.middlegame, -self.endgame)
+    }
+}
+
+/** the phase of the starting position, each knight and bishop counts 1, each rook 2 and each queen 4 */
+pub const MAX_PHASE: i32 = 24;
+
+const PIECE_TYPES: [PieceType; 6] = [PieceType::Pawn, PieceType::Knight, PieceType::Bishop, PieceType::Rook, PieceType::Queen, PieceType::King];
+
+fn phase_weight(piece_type: PieceType) -> i32 {
+    match piece_type {
+        PieceType::Knight | PieceType::Bishop => 1,
+        PieceType::Rook => 2,
+        PieceType::Queen => 4,
+        PieceType::Pawn | PieceType::King => 0
+    }
+}
+
+pub fn piece_value(piece_type: PieceType) -> TaperedScore {
+    match piece_type {
+        PieceType::Pawn => TaperedScore::new(82, 94),
+        PieceType::Knight => TaperedScore::new(337, 281),
+        PieceType::Bishop => TaperedScore::new(365, 297),
+        PieceType::Rook => TaperedScore::new(477, 512),
+        PieceType::Queen => TaperedScore::new(1025, 936),
+        PieceType::King => TaperedScore::new(0, 0)
+    }
+}
+
+// the piece-square tables are written the way white sees the board, with a8 at the top left
+
+#[rustfmt::skip]
+const PAWN_MIDDLEGAME: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+    50, 50, 50, 50, 50, 50, 50, 50,
+    10, 10, 20, 30, 30, 20, 10, 10,
+     5,  5, 10, 25, 25, 10,  5,  5,
+     0,  0,  0, 20, 20,  0,  0,  0,
+     5, -5,-10,  0,  0,-10, -5,  5,
+     5, 10, 10,-20,-20, 10, 10,  5,
+     0,  0,  0,  0,  0,  0,  0,  0
+];
+#[rustfmt::skip]
+const PAWN_ENDGAME: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+    80, 80, 80, 80, 80, 80, 80, 80,
+    50, 50, 50, 50, 50, 50, 50, 50,
+    30, 30, 30, 30, 30, 30, 30, 30,
+    20, 20, 20, 20, 20, 20, 20, 20,
+    10, 10, 10, 10, 10, 10, 10, 10,
+    10, 10, 10, 10, 10, 10, 10, 10,
+     0,  0,  0,  0,  0,  0,  0,  0
+];
+#[rustfmt::skip]
+const KNIGHT: [i32; 64] = [
+   -50,-40,-30,-30,-30,-30,-40,-50,
+   -40,-20,  0,  0,  0,  0,-20,-40,
+   -30,  0, 10, 15, 15, 10,  0,-30,
+   -30,  5, 15, 20, 20, 15,  5,-30,
+   -30,  0, 15, 20, 20, 15,  0,-30,
+   -30,  5, 10, 15, 15, 10,  5,-30,
+   -40,-20,  0,  5,  5,  0,-20,-40,
+   -50,-40,-30,-30,-30,-30,-40,-50
+];
+#[rustfmt::skip]
+const BISHOP: [i32; 64] = [
+   -20,-10,-10,-10,-10,-10,-10,-20,
+   -10,  0,  0,  0,  0,  0,  0,-10,
+   -10,  0,  5, 10, 10,  5,  0,-10,
+   -10,  5,  5, 10, 10,  5,  5,-10,
+   -10,  0, 10, 10, 10, 10,  0,-10,
+   -10, 10, 10, 10, 10, 10, 10,-10,
+   -10,  5,  0,  0,  0,  0,  5,-10,
+   -20,-10,-10,-10,-10,-10,-10,-20
+];
+#[rustfmt::skip]
+const ROOK_MIDDLEGAME: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+     5, 10, 10, 10, 10, 10, 10,  5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+     0,  0,  0,  5,  5,  0,  0,  0
+];
+#[rustfmt::skip]
+const ROOK_ENDGAME: [i32; 64] = [
+     5,  5,  5,  5,  5,  5,  5,  5,
+    10, 10, 10, 10, 10, 10, 10, 10,
+     0,  0,  0,  0,  0,  0,  0,  0,
+     0,  0,  0,  0,  0,  0,  0,  0,
+     0,  0,  0,  0,  0,  0,  0,  0,
+     0,  0,  0,  0,  0,  0,  0,  0,
+     0,  0,  0,  0,  0,  0,  0,  0,
+     0,  0,  0,  0,  0,  0,  0,  0
+];
+#[rustfmt::skip]
+const QUEEN: [i32; 64] = [
+   -20,-10,-10, -5, -5,-10,-10,-20,
+   -10,  0,  0,  0,  0,  0,  0,-10,
+   -10,  0,  5,  5,  5,  5,  0,-10,
+    -5,  0,  5,  5,  5,  5,  0, -5,
+     0,  0,  5,  5,  5,  5,  0, -5,
+   -10,  5,  5,  5,  5,  5,  0,-10,
+   -10,  0,  5,  0,  0,  0,  0,-10,
+   -20,-10,-10, -5, -5,-10,-10,-20
+];
+#[rustfmt::skip]
+const KING_MIDDLEGAME: [i32; 64] = [
+   -30,-40,-40,-50,-50,-40,-40,-30,
+   -30,-40,-40,-50,-50,-40,-40,-30,
+   -30,-40,-40,-50,-50,-40,-40,-30,
+   -30,-40,-40,-50,-50,-40,-40,-30,
+   -20,-30,-30,-40,-40,-30,-30,-20,
+   -10,-20,-20,-20,-20,-20,-20,-10,
+    20, 20,  0,  0,  0,  0, 20, 20,
+    20, 30, 10,  0,  0, 10, 30, 20
+];
+#[rustfmt::skip]
+const KING_ENDGAME: [i32; 64] = [
+   -50,-40,-30,-20,-20,-30,-40,-50,
+   -30,-20,-10,  0,  0,-10,-20,-30,
+   -30,-10, 20, 30, 30, 20,-10,-30,
+   -30,-10, 30, 40, 40, 30,-10,-30,
+   -30,-10, 30, 40, 40, 30,-10,-30,
+   -30,-10, 20, 30, 30, 20,-10,-30,
+   -30,-30,  0,  0,  0,  0,-30,-30,
+   -50,-30,-30,-30,-30,-30,-30,-50
+];
+
+/** the middlegame and endgame tables of the piece type */
+fn piece_square_tables(piece_type: PieceType) -> (&'static [i32; 64], &'static [i32; 64]) {
+    match piece_type {
+        PieceType::Pawn => (&PAWN_MIDDLEGAME, &PAWN_ENDGAME),
+        PieceType::Knight => (&KNIGHT, &KNIGHT),
+        PieceType::Bishop => (&BISHOP, &BISHOP),
+        PieceType::Rook => (&ROOK_MIDDLEGAME, &ROOK_ENDGAME),
+        PieceType::Queen => (&QUEEN, &QUEEN),
+        PieceType::King => (&KING_MIDDLEGAME, &KING_ENDGAME)
+    }
+}
+
+pub fn piece_square_value(piece_type: PieceType, color: Color, square: u8) -> TaperedScore {
+    let (x, y) = (square % 8, square / 8);
+    // the tables start at rank 8, so white squares are flipped and black squares, seen from the other side, aren't
+    let index = match color {
+        Color::White => x + 8 * (7 - y),
+        Color::Black => x + 8 * y
+    } as usize;
+    let (middlegame, endgame) = piece_square_tables(piece_type);
+    TaperedScore::new(middlegame[index], endgame[index])
+}
+
+const FILE_A: u64 = 0x0101010101010101;
+
+fn file_mask(file: u8) -> u64 {
+    FILE_A << file
+}
+fn adjacent_files(file: u8) -> u64 {
+    (if file > 0 { file_mask(file - 1) } else { 0 }) | (if file < 7 { file_mask(file + 1) } else { 0 })
+}
+/** the ranks strictly in front of the rank, from the point of view of the color */
+fn ranks_in_front(rank: u8, color: Color) -> u64 {
+    match color {
+        Color::White => if rank == 7 { 0 } else { u64::MAX << (8 * (rank + 1)) },
+        Color::Black => if rank == 0 { 0 } else { u64::MAX >> (8 * (8 - rank)) }
+    }
+}
+
+const DOUBLED_PAWN: TaperedScore = TaperedScore::new(-10, -20);
+const ISOLATED_PAWN: TaperedScore = TaperedScore::new(-10, -15);
+/** indexed by how many ranks the pawn has advanced */
+const PASSED_PAWN: [TaperedScore; 8] = [
+    TaperedScore::new(0, 0), TaperedScore::new(5, 10), TaperedScore::new(5, 15), TaperedScore::new(10, 25),
+    TaperedScore::new(20, 45), TaperedScore::new(35, 75), TaperedScore::new(60, 120), TaperedScore::new(0, 0)
+];
+
+/** doubled, isolated and passed pawns of the color */
+pub fn pawn_structure(chessboard: &Chessboard, color: Color) -> TaperedScore {
+    let pawns = chessboard.pieces(PieceType::Pawn, color);
+    let enemy_pawns = chessboard.pieces(PieceType::Pawn, color.opposite());
+    let mut score = TaperedScore::default();
+
+    for file in 0..8 {
+        let on_file = (pawns & file_mask(file)).count_ones() as i32;
+        if on_file > 1 {
+            score += TaperedScore::new(DOUBLED_PAWN.middlegame * (on_file - 1), DOUBLED_PAWN.endgame * (on_file - 1));
+        }
+    }
+    for square in bitboard::squares(pawns) {
+        let (file, rank) = (square % 8, square / 8);
+        if pawns & adjacent_files(file) == 0 { score += ISOLATED_PAWN; }
+        if enemy_pawns & (file_mask(file) | adjacent_files(file)) & ranks_in_front(rank, color) == 0 {
+            let advanced = match color { Color::White => rank, Color::Black => 7 - rank };
+            score += PASSED_PAWN[advanced as usize];
+        }
+    }
+    score
+}
+
+fn mobility_weight(piece_type: PieceType) -> TaperedScore {
+    match piece_type {
+        PieceType::Knight => TaperedScore::new(4, 4),
+        PieceType::Bishop => TaperedScore::new(5, 5),
+        PieceType::Rook => TaperedScore::new(2, 4),
+        PieceType::Queen => TaperedScore::new(1, 2),
+        PieceType::Pawn | PieceType::King => TaperedScore::new(0, 0)
+    }
+}
+
+fn attacks(piece_type: PieceType, square: u8, occupancy: u64) -> u64 {
+    match piece_type {
+        PieceType::Knight => bitboard::knight_attacks(square),
+        PieceType::Bishop => bitboard::bishop_attacks(square, occupancy),
+        PieceType::Rook => bitboard::rook_attacks(square, occupancy),
+        PieceType::Queen => bitboard::bishop_attacks(square, occupancy) | bitboard::rook_attacks(square, occupancy),
+        PieceType::King => bitboard::king_attacks(square),
+        PieceType::Pawn => 0
+    }
+}
+
+/** a bonus for every square the knights, bishops, rooks and queens of the color can move to */
+pub fn mobility(chessboard: &Chessboard, color: Color) -> TaperedScore {
+    let (occupancy, own) = (chessboard.occupancy(), chessboard.color_occupancy(color));
+    let mut score = TaperedScore::default();
+    for piece_type in [PieceType::Knight, PieceType::Bishop, PieceType::Rook, PieceType::Queen] {
+        let weight = mobility_weight(piece_type);
+        for square in bitboard::squares(chessboard.pieces(piece_type, color)) {
+            let moves = (attacks(piece_type, square, occupancy) & !own).count_ones() as i32;
+            score += TaperedScore::new(weight.middlegame * moves, weight.endgame * moves);
+        }
+    }
+    score
+}
+
+const PAWN_SHIELD: TaperedScore = TaperedScore::new(10, 0);
+const KING_ZONE_ATTACK: TaperedScore = TaperedScore::new(-6, -1);
+
+/**
+    pawns sheltering the king of the color, and the squares around it attacked by enemy pieces. It mostly
+    matters in the middlegame, when there is enough material to mount an attack
+*/
+pub fn king_safety(chessboard: &Chessboard, color: Color) -> TaperedScore {
+    let Some(king) = chessboard.king_square(color) else { return TaperedScore::default() };
+    let mut score = TaperedScore::default();
+
+    // the pawns on the king's file and the ones next to it, on the two ranks in front of it
+    let (file, rank) = (king % 8, king / 8);
+    let two_ranks = match color {
+        Color::White => ranks_in_front(rank, color) & !ranks_in_front((rank + 2).min(7), color),
+        Color::Black => ranks_in_front(rank, color) & !ranks_in_front(rank.saturating_sub(2), color)
+    };
+    let shield = chessboard.pieces(PieceType::Pawn, color) & (file_mask(file) | adjacent_files(file)) & two_ranks;
+    let shield_pawns = shield.count_ones() as i32;
+    score += TaperedScore::new(PAWN_SHIELD.middlegame * shield_pawns, PAWN_SHIELD.endgame * shield_pawns);
+
+    let zone = bitboard::king_attacks(king) | 1 << king;
+    let occupancy = chessboard.occupancy();
+    let mut attacked = 0;
+    for piece_type in [PieceType::Knight, PieceType::Bishop, PieceType::Rook, PieceType::Queen] {
+        for square in bitboard::squares(chessboard.pieces(piece_type, color.opposite())) {
+            attacked += (attacks(piece_type, square, occupancy) & zone).count_ones() as i32;
+        }
+    }
+    score + TaperedScore::new(KING_ZONE_ATTACK.middlegame * attacked, KING_ZONE_ATTACK.endgame * attacked)
+}
+
+/** every term of the evaluation, from white's point of view */
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Breakdown {
+    pub material: TaperedScore,
+    pub piece_squares: TaperedScore,
+    pub pawn_structure: TaperedScore,
+    pub mobility: TaperedScore,
+    pub king_safety: TaperedScore,
+    /** from 0 (only kings and pawns) to MAX_PHASE (all the pieces) */
+    pub phase: i32
+}
+impl Breakdown {
+    pub fn terms(&self) -> [(&'static str, TaperedScore); 5] {
+        [
+            ("material", self.material),
+            ("piece squares", self.piece_squares),
+            ("pawn structure", self.pawn_structure),
+            ("mobility", self.mobility),
+            ("king safety", self.king_safety)
+        ]
+    }
+    pub fn total(&self) -> TaperedScore {
+        self.terms().iter().fold(TaperedScore::default(), |total, (_, score)| total + *score)
+    }
+    /** the final score from white's point of view */
+    pub fn score(&self) -> i32 {
+        self.total().blend(self.phase)
+    }
+}
+impl Display for Breakdown {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        writeln!(f, "{:<16}{:>8}{:>8}{:>8}", "term", "mg", "eg", "blended")?;
+        for (name, score) in self.terms() {
+            writeln!(f, "{name:<16}{:>8}{:>8}{:>8}", score.middlegame, score.endgame, score.blend(self.phase))?;
+        }
+        let total = self.total();
+        writeln!(f, "{:<16}{:>8}{:>8}{:>8}", "total", total.middlegame, total.endgame, self.score())?;
+        write!(f, "phase {}/{MAX_PHASE}", self.phase)
+    }
+}
+
+/** evaluates the position term by term, to see why it got the score it did */
+pub fn breakdown(chessboard: &Chessboard) -> Breakdown {
+    let mut breakdown = Breakdown::default();
+    for (color, sign) in [(Color::White, 1), (Color::Black, -1)] {
+        let signed = |score: TaperedScore| if sign > 0 { score } else { -score };
+
+        for piece_type in PIECE_TYPES {
+            for square in bitboard::squares(chessboard.pieces(piece_type, color)) {
+                breakdown.material += signed(piece_value(piece_type));
+                breakdown.piece_squares += signed(piece_square_value(piece_type, color, square));
+                breakdown.phase += phase_weight(piece_type);
+            }
+        }
+        breakdown.pawn_structure += signed(pawn_structure(chessboard, color));
+        breakdown.mobility += signed(mobility(chessboard, color));
+        breakdown.king_safety += signed(king_safety(chessboard, color));
+    }
+    // promotions can leave more material than the starting position
+    breakdown.phase = breakdown.phase.min(MAX_PHASE);
+    breakdown
+}
+
+/** the static evaluation of the position in centipawns, from the point of view of the player to play */
+pub fn evaluate(chessboard: &Chessboard) -> i32 {
+    let score = breakdown(chessboard).score();
+    if chessboard.to_play == Color::White { score } else { -score }
+}
+
+/** the same position with the colors swapped, which has to get the opposite evaluation */
+#[cfg(test)]
+fn mirror(fen: &str) -> String {
+    let fields: Vec<&str> = fen.split(' ').collect();
+    let swap_case = |text: &str| text.chars().map(|character| {
+        if character.is_ascii_uppercase() { character.to_ascii_lowercase() } else { character.to_ascii_uppercase() }
+    }).collect::<String>();
+    let placement: Vec<String> = fields[0].split('/').rev().map(swap_case).collect();
+    let side = if fields[1] == "w" { "b" } else { "w" };
+    let castling: String = "KQkq".chars().filter(|&right| swap_case(fields[2]).contains(right)).collect();
+    let castling = if castling.is_empty() { "-".to_owned() } else { castling };
+    format!("{} {side} {castling} - {} {}", placement.join("/"), fields[4], fields[5])
+}
+
+#[test]
+fn evaluation() {
+    let evaluation = |fen: &str| evaluate(&Chessboard::from_fen(fen).expect("FEN is invalid"));
+    let terms = |fen: &str| breakdown(&Chessboard::from_fen(fen).expect("FEN is invalid"));
+
+    let start = terms("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+    assert_eq!(start.phase, MAX_PHASE);
+    assert_eq!(start.total(), TaperedScore::default());
+    assert_eq!(terms("8/4k3/8/8/3P4/8/8/4K3 w - - 0 1").phase, 0);
+
+    let positions = [
+        "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "6k1/5ppp/8/8/8/8/8/R5K1 b - - 0 1"
+    ];
+    for fen in positions {
+        assert_eq!(evaluation(fen), evaluation(&mirror(fen)), "{fen} and its mirror got different scores");
+        assert_eq!(terms(fen).to_string().lines().count(), 8);
+    }
+
+    // the side to play sees the score from its own point of view
+    assert!(evaluation("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1") > 150);
+    assert!(evaluation("6k1/5ppp/8/8/8/8/8/R5K1 b - - 0 1") < -150);
+    // a passed pawn about to promote is worth more than one at home, and doubled isolated pawns are bad
+    assert!(pawn_structure(&Chessboard::from_fen("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1").unwrap(), Color::White).endgame > 100);
+    assert_eq!(pawn_structure(&Chessboard::from_fen("4k3/3p4/8/8/8/2P5/2P5/4K3 w - - 0 1").unwrap(), Color::White), DOUBLED_PAWN + ISOLATED_PAWN + ISOLATED_PAWN);
+    // a king without its pawns is less safe
+    let sheltered = king_safety(&Chessboard::from_fen("r5k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1").unwrap(), Color::White);
+    let exposed = king_safety(&Chessboard::from_fen("r5k1/5ppp/8/8/8/8/PPP5/R5K1 w - - 0 1").unwrap(), Color::White);
+    assert!(sheltered.middlegame > exposed.middlegame);
+}
@@ -0,0 +1,13 @@
+pub mod pieces;
+pub mod chessboard;
+pub mod bitboard;
+pub mod book;
+pub mod dtm;
+pub mod eval;
+pub mod movepick;
+pub mod pgn;
+pub mod search;
+pub mod syzygy;
+pub mod timeman;
+pub mod tt;
+mod zobrist;
@@ -1,25 +1,272 @@
-mod pieces;
-mod chessboard;
+use std::io::BufRead;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
 
-use chessboard::{Chessboard, Move};
+use rust_chess::chessboard::{Chessboard, Move, STARTING_FEN};
+use rust_chess::pieces::Color;
+use rust_chess::search::{moves_to_mate, Limits, Search, SearchResult};
+use rust_chess::syzygy::Tablebases;
+use rust_chess::timeman::{Clock, MOVE_OVERHEAD};
+use rust_chess::tt::DEFAULT_MEGABYTES;
 
-fn main() {
-    let mut chessboard = Chessboard::from_fen("8/8/8/8/2n5/n7/3N4/1N6 b - - 0 1").expect("FEN is invalid");
-    println!("{chessboard}");
+// a chess engine speaking the Universal Chess Interface over stdin and stdout, read
+// https://www.wbec-ridderkerk.nl/html/UCIProtocol.html to know better how the protocol works
+
+const NAME: &str = "rust-chess";
+const AUTHOR: &str = "GDOR-11";
 
-    let moves = chessboard.get_legal_moves();
+/** the biggest transposition table that can be asked for, in megabytes */
+const MAX_HASH: usize = 4096;
+
+/** the limits sent along with the go command */
+#[derive(Debug, Default, Clone)]
+struct GoOptions {
+    depth: Option<u32>,
+    nodes: Option<u64>,
+    movetime: Option<Duration>,
+    /** the remaining time and increment of each player, indexed by Color */
+    time: [Option<Duration>; 2],
+    increment: [Option<Duration>; 2],
+    movestogo: Option<u32>,
+    infinite: bool
+}
 
-    for r#move in &moves { println!("{move}"); }
+impl GoOptions {
+    fn parse(arguments: &[&str]) -> Self {
+        let mut options = GoOptions::default();
+        let mut arguments = arguments.iter();
+        let milliseconds = |value: Option<&&str>| value.and_then(|value| value.parse().ok()).map(Duration::from_millis);
 
-    // check all 4096 moves
-    for start_y in 0..8 {
-        for start_x in 0..8 {
-            for end_y in 0..8 {
-                for end_x in 0..8 {
-                    let r#move = Move::new(start_x, start_y, end_x, end_y, None);
-                    assert_eq!(chessboard.is_legal_move(r#move), Ok(moves.contains(&r#move)));
-                }
+        while let Some(&argument) = arguments.next() {
+            match argument {
+                "depth" => options.depth = arguments.next().and_then(|value| value.parse().ok()),
+                "nodes" => options.nodes = arguments.next().and_then(|value| value.parse().ok()),
+                "movetime" => options.movetime = milliseconds(arguments.next()),
+                "wtime" => options.time[Color::White as usize] = milliseconds(arguments.next()),
+                "btime" => options.time[Color::Black as usize] = milliseconds(arguments.next()),
+                "winc" => options.increment[Color::White as usize] = milliseconds(arguments.next()),
+                "binc" => options.increment[Color::Black as usize] = milliseconds(arguments.next()),
+                "movestogo" => options.movestogo = arguments.next().and_then(|value| value.parse().ok()),
+                "infinite" => options.infinite = true,
+                _ => ()
             }
         }
+        options
+    }
+
+    /** the clock of the player, unless a fixed time to think was given */
+    fn clock(&self, color: Color) -> Option<Clock> {
+        if self.movetime.is_some() { return None; }
+        let time = self.time[color as usize]?;
+        Some(Clock { time, increment: self.increment[color as usize].unwrap_or_default(), moves_to_go: self.movestogo })
+    }
+
+    /** whether nothing limits the search of the player, which then thinks until stop */
+    fn is_bare(&self, color: Color) -> bool {
+        self.depth.is_none() && self.nodes.is_none() && self.movetime.is_none() && self.time[color as usize].is_none()
+    }
+
+    fn limits(&self, color: Color) -> Limits {
+        if self.infinite { return Limits::default(); }
+        let time = self.movetime.map(|movetime| movetime.saturating_sub(MOVE_OVERHEAD));
+        Limits { depth: self.depth, nodes: self.nodes, time, clock: self.clock(color) }
     }
 }
+
+/** the info line UCI expects after every depth, with mates in moves instead of centipawns */
+fn print_info(result: &SearchResult) {
+    let score = match moves_to_mate(result.score) {
+        Some(moves) => format!("mate {moves}"),
+        None => format!("cp {}", result.score)
+    };
+    let principal_variation: Vec<String> = result.principal_variation.iter().map(Move::to_string).collect();
+    println!(
+        "info depth {} score {score} nodes {} time {} hashfull {} pv {}",
+        result.depth, result.nodes, result.time.as_millis(), result.hashfull, principal_variation.join(" ")
+    );
+}
+
+struct Engine {
+    chessboard: Chessboard,
+    /** None while it is thinking in its own thread */
+    search: Option<Search>,
+    thread: Option<JoinHandle<Search>>,
+    stop: Arc<AtomicBool>,
+    /** if the running search only ends with stop */
+    infinite: bool
+}
+
+impl Engine {
+    fn new() -> Self {
+        let search = Search::new(Limits::default());
+        Engine {
+            chessboard: Chessboard::from_fen(STARTING_FEN).expect("the starting FEN is invalid"),
+            stop: search.stop_flag(),
+            search: Some(search),
+            thread: None,
+            infinite: false
+        }
+    }
+
+    fn stop_search(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.wait_for_search();
+    }
+    /** lets the running search end by itself before anything else is done, unless it would never end */
+    fn wait_for_search(&mut self) {
+        if self.infinite { self.stop.store(true, Ordering::Relaxed); }
+        if let Some(thread) = self.thread.take() { self.search = Some(thread.join().expect("the search thread panicked")); }
+    }
+    /** the search, once it isn't thinking anymore */
+    fn idle_search(&mut self) -> &mut Search {
+        self.wait_for_search();
+        self.search.as_mut().expect("the search is always back once its thread is joined")
+    }
+
+    /** setoption name <name> [value <value>] */
+    fn set_option(&mut self, arguments: &[&str]) -> Result<(), String> {
+        let value_index = arguments.iter().position(|&word| word == "value").unwrap_or(arguments.len());
+        let name = arguments.get(1..value_index).unwrap_or_default().join(" ");
+        let value = arguments.get(value_index + 1..).unwrap_or_default().join(" ");
+
+        match name.to_lowercase().as_str() {
+            "hash" => {
+                let megabytes = value.parse().ok().filter(|megabytes| (1..=MAX_HASH).contains(megabytes))
+                    .ok_or_else(|| format!("Hash has to be between 1 and {MAX_HASH}, not '{value}'"))?;
+                self.idle_search().resize_table(megabytes);
+            }
+            "clear hash" => self.idle_search().new_game(),
+            // GUIs send <empty> to mean no path
+            "syzygypath" => {
+                let tablebases = if value.is_empty() || value == "<empty>" {
+                    None
+                } else {
+                    Some(Tablebases::new(&value).map_err(|error| format!("can't read the tablebases in '{value}': {error}"))?)
+                };
+                self.idle_search().set_tablebases(tablebases.map(Arc::new));
+            }
+            _ => return Err(format!("unknown option '{name}'"))
+        }
+        Ok(())
+    }
+
+    /** position [startpos | fen <fen>] [moves <move> ...] */
+    fn set_position(&mut self, arguments: &[&str]) -> Result<(), String> {
+        let moves_index = arguments.iter().position(|&argument| argument == "moves").unwrap_or(arguments.len());
+        let mut chessboard = match arguments.first() {
+            Some(&"startpos") => Chessboard::from_fen(STARTING_FEN).expect("the starting FEN is invalid"),
+            Some(&"fen") => Chessboard::from_fen(&arguments[1..moves_index].join(" ")).map_err(|error| format!("invalid FEN: {error}"))?,
+            _ => return Err("position needs either startpos or fen".to_owned())
+        };
+        for uci in arguments.iter().skip(moves_index + 1) {
+            let r#move = chessboard.parse_uci_move(uci).map_err(|error| error.to_string())?;
+            chessboard.make_move(r#move);
+        }
+        self.chessboard = chessboard;
+        Ok(())
+    }
+
+    fn go(&mut self, options: GoOptions) {
+        let limits = options.limits(self.chessboard.to_play);
+        self.idle_search().set_limits(limits);
+        let mut search = self.search.take().expect("the search is idle");
+        self.infinite = options.infinite;
+        let mut chessboard = self.chessboard.clone();
+        let stop = self.stop.clone();
+
+        self.thread = Some(thread::spawn(move || {
+            let best_move = search.run(&mut chessboard, print_info).best_move;
+            // in infinite mode the best move can only be sent after stop, even if the search is over
+            while options.infinite && !stop.load(Ordering::Relaxed) { thread::sleep(Duration::from_millis(1)); }
+            match best_move {
+                Some(best_move) => println!("bestmove {best_move}"),
+                None => println!("bestmove 0000")
+            }
+            search
+        }));
+    }
+
+    /** handles one line sent by the GUI, returning false when it is time to quit */
+    fn handle_command(&mut self, line: &str) -> bool {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let Some((&command, arguments)) = words.split_first() else { return true };
+
+        match command {
+            "uci" => {
+                println!("id name {NAME}");
+                println!("id author {AUTHOR}");
+                println!("option name Hash type spin default {DEFAULT_MEGABYTES} min 1 max {MAX_HASH}");
+                println!("option name Clear Hash type button");
+                println!("option name SyzygyPath type string default <empty>");
+                println!("uciok");
+            }
+            "isready" => println!("readyok"),
+            "ucinewgame" => {
+                self.idle_search().new_game();
+                self.chessboard = Chessboard::from_fen(STARTING_FEN).expect("the starting FEN is invalid");
+            }
+            "position" => {
+                self.wait_for_search();
+                if let Err(error) = self.set_position(arguments) { println!("info string {error}"); }
+            }
+            "go" => {
+                let mut options = GoOptions::parse(arguments);
+                // a bare go means thinking until stop, and so does a go with only the clock of the other player
+                if options.is_bare(self.chessboard.to_play) { options.infinite = true; }
+                self.go(options);
+            }
+            "stop" => self.stop_search(),
+            "setoption" => {
+                if let Err(error) = self.set_option(arguments) { println!("info string {error}"); }
+            }
+            "quit" => {
+                self.stop_search();
+                return false;
+            }
+            "debug" | "register" | "ponderhit" => (),
+            _ => println!("info string unknown command '{command}'")
+        }
+        true
+    }
+}
+
+fn main() {
+    let mut engine = Engine::new();
+    for line in std::io::stdin().lock().lines() {
+        let Ok(line) = line else { break };
+        if !engine.handle_command(&line) { return; }
+    }
+    engine.wait_for_search();
+}
+
+#[test]
+fn uci_commands() {
+    let options = GoOptions::parse(&["wtime", "60000", "btime", "30000", "winc", "1000", "movestogo", "20"]);
+    let clock = |time, increment| Some(Clock { time: Duration::from_millis(time), increment: Duration::from_millis(increment), moves_to_go: Some(20) });
+    assert_eq!(options.limits(Color::White).clock, clock(60000, 1000));
+    assert_eq!(options.limits(Color::Black).clock, clock(30000, 0));
+    let movetime = GoOptions::parse(&["movetime", "1000", "wtime", "60000"]).limits(Color::White);
+    assert_eq!((movetime.time, movetime.clock), (Some(Duration::from_millis(1000) - MOVE_OVERHEAD), None));
+    assert_eq!(GoOptions::parse(&["depth", "5", "nodes", "1000"]).limits(Color::Black), Limits { depth: Some(5), nodes: Some(1000), time: None, clock: None });
+    assert_eq!(GoOptions::parse(&["infinite", "depth", "5"]).limits(Color::Black), Limits::default());
+    // the clock of the other player doesn't limit the search
+    let other_clock = GoOptions::parse(&["btime", "5000"]);
+    assert!(other_clock.is_bare(Color::White) && !other_clock.is_bare(Color::Black));
+    assert!(GoOptions::parse(&[]).is_bare(Color::White) && !GoOptions::parse(&["nodes", "1000"]).is_bare(Color::White));
+
+    let mut engine = Engine::new();
+    assert_eq!(engine.set_position(&["startpos", "moves", "e2e4", "e7e5", "g1f3"]), Ok(()));
+    assert_eq!(engine.chessboard.to_fen(), "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2");
+    assert_eq!(engine.set_position(&["fen", "6k1/5ppp/8/8/8/8/8/R5K1", "w", "-", "-", "0", "1"]), Ok(()));
+    assert!(engine.set_position(&["startpos", "moves", "e2e5"]).is_err());
+    assert_eq!(engine.chessboard.to_fen(), "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+
+    assert_eq!(engine.set_option(&["name", "Hash", "value", "1"]), Ok(()));
+    assert_eq!(engine.set_option(&["name", "Clear", "Hash"]), Ok(()));
+    assert!(engine.set_option(&["name", "Hash", "value", "0"]).is_err());
+    assert!(engine.set_option(&["name", "Threads", "value", "2"]).is_err());
+    assert_eq!(engine.set_option(&["name", "SyzygyPath", "value", "<empty>"]), Ok(()));
+    assert!(engine.set_option(&["name", "SyzygyPath", "value", "/there/are/no/tablebases/here"]).is_err());
+}
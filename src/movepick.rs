@@ -0,0 +1,188 @@
+use crate::bitboard::square_index;
+use crate::chessboard::{Chessboard, Move, SpecialMove};
+use crate::pieces::{Color, PieceType};
+use crate::search::MAX_PLY;
+
+// alpha-beta cuts off the most when the best move is searched first, so moves are tried in the order they are most
+// likely to be good (https://www.chessprogramming.org/Move_Ordering): the move the transposition table remembers,
+// then captures that don't lose material by static exchange evaluation, the most valuable victim by the least
+// valuable attacker first (MVV-LVA), then the quiet moves that caused cutoffs at the same ply before (killers), then
+// the other quiet moves by how often they caused cutoffs anywhere (history), and finally the losing captures
+
+const HASH_MOVE: i32 = 1_000_000;
+const CAPTURE: i32 = 100_000;
+const LOSING_CAPTURE: i32 = -100_000;
+const KILLERS: [i32; 2] = [90_000, 89_000];
+/** history scores are kept below this, so they never reach the killers */
+const MAX_HISTORY: i32 = 50_000;
+
+/** 1 for pawns up to 6 for kings, the order MVV-LVA cares about */
+fn ordering_value(piece_type: PieceType) -> i32 {
+    match piece_type {
+        PieceType::Pawn => 1,
+        PieceType::Knight => 2,
+        PieceType::Bishop => 3,
+        PieceType::Rook => 4,
+        PieceType::Queen => 5,
+        PieceType::King => 6
+    }
+}
+
+/** checks if the move takes a piece, including en passant */
+pub fn is_capture(chessboard: &Chessboard, r#move: Move) -> bool {
+    r#move.special_move == Some(SpecialMove::EnPassant) || chessboard.get(r#move.to.x, r#move.to.y).is_ok_and(|piece| piece.is_some())
+}
+
+/**
+    captures and queen promotions, the moves that change the material on the board. Underpromotions are almost never
+    better than the queen, so unless they capture they are treated as quiet moves
+*/
+pub fn is_tactical(chessboard: &Chessboard, r#move: Move) -> bool {
+    is_capture(chessboard, r#move) || r#move.special_move == Some(SpecialMove::Promotion(PieceType::Queen))
+}
+
+/** what the search learns about quiet moves that cause cutoffs, used to order the moves of other nodes */
+pub struct Heuristics {
+    /** the last two quiet moves that caused a cutoff at each ply */
+    killers: Vec<[Option<Move>; 2]>,
+    /** indexed by the color moving and then by the from and to squares (x + 8 * y) */
+    history: Vec<[[i32; 64]; 64]>
+}
+
+impl Default for Heuristics {
+    fn default() -> Self {
+        Heuristics { killers: vec![[None; 2]; MAX_PLY + 1], history: vec![[[0; 64]; 64]; 2] }
+    }
+}
+
+impl Heuristics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /** forgets everything, like at the start of a new game */
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /** killers only make sense for the search that found them, but history is still worth something, so it fades */
+    pub fn new_search(&mut self) {
+        self.killers.fill([None; 2]);
+        for from in self.history.iter_mut().flatten() {
+            for score in from.iter_mut() { *score /= 2; }
+        }
+    }
+
+    pub fn killers(&self, ply: usize) -> [Option<Move>; 2] {
+        self.killers.get(ply).copied().unwrap_or_default()
+    }
+
+    pub fn history(&self, color: Color, r#move: Move) -> i32 {
+        self.history[color as usize][square_index(r#move.from) as usize][square_index(r#move.to) as usize]
+    }
+
+    /** remembers a quiet move that made the search cut off, searched to that depth */
+    pub fn record_cutoff(&mut self, color: Color, r#move: Move, ply: usize, depth: u32) {
+        if let Some(killers) = self.killers.get_mut(ply) {
+            if killers[0] != Some(r#move) {
+                killers[1] = killers[0];
+                killers[0] = Some(r#move);
+            }
+        }
+
+        let score = &mut self.history[color as usize][square_index(r#move.from) as usize][square_index(r#move.to) as usize];
+        // deeper cutoffs say more about the move
+        *score += (depth * depth) as i32;
+        if *score >= MAX_HISTORY {
+            for from in self.history[color as usize].iter_mut() {
+                for score in from.iter_mut() { *score /= 2; }
+            }
+        }
+    }
+}
+
+/** hands out the moves of a position from the most to the least promising */
+pub struct MovePicker {
+    moves: Vec<(Move, i32)>
+}
+
+impl MovePicker {
+    pub fn new(chessboard: &Chessboard, moves: Vec<Move>, hash_move: Option<Move>, ply: usize, heuristics: &Heuristics) -> Self {
+        let killers = heuristics.killers(ply);
+        let moves = moves.into_iter().map(|r#move| {
+            let score = if Some(r#move) == hash_move {
+                HASH_MOVE
+            } else if is_tactical(chessboard, r#move) {
+                let band = if chessboard.see(r#move) >= 0 { CAPTURE } else { LOSING_CAPTURE };
+                band + mvv_lva(chessboard, r#move)
+            } else if let Some(index) = killers.iter().position(|&killer| killer == Some(r#move)) {
+                KILLERS[index]
+            } else {
+                heuristics.history(chessboard.to_play, r#move)
+            };
+            (r#move, score)
+        }).collect();
+        MovePicker { moves }
+    }
+}
+
+/** the most valuable victim first, and among those the least valuable attacker, with promotions as extra value */
+pub fn mvv_lva(chessboard: &Chessboard, r#move: Move) -> i32 {
+    let piece_type = |x, y| chessboard.get(x, y).ok().flatten().map(|piece| piece.piece_type);
+    let victim = match r#move.special_move {
+        Some(SpecialMove::EnPassant) => ordering_value(PieceType::Pawn),
+        _ => piece_type(r#move.to.x, r#move.to.y).map_or(0, ordering_value)
+    };
+    let attacker = piece_type(r#move.from.x, r#move.from.y).map_or(0, ordering_value);
+    let promotion = match r#move.special_move {
+        Some(SpecialMove::Promotion(piece_type)) => ordering_value(piece_type),
+        _ => 0
+    };
+    10 * (victim + promotion) - attacker
+}
+
+impl Iterator for MovePicker {
+    type Item = Move;
+
+    /** a selection sort done one move at a time, since a cutoff usually comes before all the moves are needed */
+    fn next(&mut self) -> Option<Move> {
+        let best = self.moves.iter().enumerate().max_by_key(|(index, (_, score))| (*score, std::cmp::Reverse(*index)))?.0;
+        Some(self.moves.swap_remove(best).0)
+    }
+}
+
+#[test]
+fn move_ordering() {
+    let r#move = |string: &str| Move::from_string(string, None).unwrap();
+    // white can take the queen with the pawn or with the rook, or take a defended pawn with the queen
+    let chessboard = Chessboard::from_fen("4k3/8/2n5/3q2p1/4P2p/8/8/K2RQ3 w - - 0 1").expect("FEN is invalid");
+    let mut heuristics = Heuristics::new();
+    heuristics.record_cutoff(Color::White, r#move("a1b1"), 3, 4);
+    heuristics.record_cutoff(Color::White, r#move("a1b2"), 3, 2);
+    heuristics.record_cutoff(Color::White, r#move("d1d2"), 5, 6);
+
+    let ordered: Vec<Move> = MovePicker::new(&chessboard, chessboard.get_legal_moves(), Move::from_string("e1e2", None), 3, &heuristics).collect();
+    assert_eq!(ordered.len(), chessboard.get_legal_moves().len());
+    assert_eq!(ordered.last(), Some(&r#move("e1h4")));
+    assert_eq!(ordered[..6], [
+        r#move("e1e2"), // the hash move
+        r#move("e4d5"), r#move("d1d5"), // the pawn takes the queen before the rook does
+        r#move("a1b2"), r#move("a1b1"), // the killers of the ply, latest first
+        r#move("d1d2") // the best history score
+    ]);
+    assert_eq!(heuristics.history(Color::White, r#move("d1d2")), 36);
+    assert_eq!(heuristics.history(Color::Black, r#move("d1d2")), 0);
+
+    heuristics.new_search();
+    assert_eq!(heuristics.killers(3), [None, None]);
+    assert_eq!(heuristics.history(Color::White, r#move("d1d2")), 18);
+    // a quiet underpromotion comes after the killers, only the queen promotion is ordered with the captures
+    let chessboard = Chessboard::from_fen("4k3/1P6/8/8/8/8/8/K7 w - - 0 1").expect("FEN is invalid");
+    heuristics.record_cutoff(Color::White, r#move("a1a2"), 3, 1);
+    let promotion = |piece_type| Move::from_string("b7b8", Some(SpecialMove::Promotion(piece_type))).unwrap();
+    let ordered: Vec<Move> = MovePicker::new(&chessboard, chessboard.get_legal_moves(), None, 3, &heuristics).collect();
+    assert_eq!(ordered[..2], [promotion(PieceType::Queen), r#move("a1a2")]);
+    assert!(!is_tactical(&chessboard, promotion(PieceType::Knight)));
+
+    assert!(is_capture(&Chessboard::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap(), Move::from_string("e5d6", Some(SpecialMove::EnPassant)).unwrap()));
+}
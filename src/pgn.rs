@@ -0,0 +1,221 @@
+use std::fmt::{Display, Formatter, Error};
+
+use crate::chessboard::{Chessboard, FenError, Move, SanError, STARTING_FEN};
+
+mod reader;
+mod writer;
+
+pub use reader::{parse, parse_each};
+
+// read https://www.thechessdrum.net/PGN_Reference.txt to know better how PGN works
+
+/** the tags every PGN game has, in the order they have to be written */
+pub const SEVEN_TAG_ROSTER: [&str; 7] = ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+    /** the game is still going on, was abandoned or the result is unknown */
+    #[default]
+    Unknown
+}
+impl GameResult {
+    pub fn from_string(string: &str) -> Option<Self> {
+        match string {
+            "1-0" => Some(GameResult::WhiteWins),
+            "0-1" => Some(GameResult::BlackWins),
+            "1/2-1/2" => Some(GameResult::Draw),
+            "*" => Some(GameResult::Unknown),
+            _ => None
+        }
+    }
+}
+impl Display for GameResult {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "{}", match self {
+            GameResult::WhiteWins => "1-0",
+            GameResult::BlackWins => "0-1",
+            GameResult::Draw => "1/2-1/2",
+            GameResult::Unknown => "*"
+        })
+    }
+}
+
+/** a move of a game along with everything written about it */
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct MoveNode {
+    pub r#move: Move,
+    /** Numeric Annotation Glyphs, like $1 for "good move". Suffixes like ! and ?? are stored as their NAGs */
+    pub nags: Vec<u8>,
+    /** the comments written after the move, joined by spaces */
+    pub comment: Option<String>,
+    /** alternatives to this move, starting from the position before it was made */
+    pub variations: Vec<Variation>
+}
+impl MoveNode {
+    pub fn new(r#move: Move) -> Self {
+        Self { r#move, nags: vec![], comment: None, variations: vec![] }
+    }
+}
+
+/** a sequence of moves, either the main line of a game or a variation inside it */
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Variation {
+    /** the comments written before the first move */
+    pub comment: Option<String>,
+    pub moves: Vec<MoveNode>
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Game {
+    /** tag pairs in the order they were read */
+    pub tags: Vec<(String, String)>,
+    pub mainline: Variation,
+    pub result: GameResult
+}
+
+impl Game {
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter().find(|(tag, _)| tag == name).map(|(_, value)| value.as_str())
+    }
+    /** replaces the value of the tag, or adds the tag at the end if the game doesn't have it */
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+            Some((_, old_value)) => *old_value = value.to_owned(),
+            None => self.tags.push((name.to_owned(), value.to_owned()))
+        }
+    }
+
+    /** the position given by the FEN tag (used along with SetUp "1"), or the standard starting position */
+    pub fn starting_position(&self) -> Result<Chessboard, FenError> {
+        Chessboard::from_fen(self.tag("FEN").unwrap_or(STARTING_FEN))
+    }
+    /** the position at the end of the main line */
+    pub fn final_position(&self) -> Result<Chessboard, FenError> {
+        let mut chessboard = self.starting_position()?;
+        for node in &self.mainline.moves { chessboard.make_move(node.r#move); }
+        Ok(chessboard)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum PgnError {
+    /** something that doesn't belong where it was found */
+    UnexpectedToken { line: usize, token: String },
+    /** the text ended in the middle of a tag, comment or variation */
+    UnexpectedEnd { line: usize },
+    /** the FEN tag of the game starting at the line is invalid */
+    InvalidFen { line: usize, error: FenError },
+    /** a move that isn't legal, or couldn't be read */
+    IllegalMove { line: usize, error: SanError }
+}
+
+impl Display for PgnError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match self {
+            PgnError::UnexpectedToken { line, token } => write!(f, "line {line}: unexpected '{token}'"),
+            PgnError::UnexpectedEnd { line } => write!(f, "line {line}: unexpected end of the text"),
+            PgnError::InvalidFen { line, error } => write!(f, "line {line}: invalid FEN tag, {error}"),
+            PgnError::IllegalMove { line, error } => write!(f, "line {line}: {error}")
+        }
+    }
+}
+
+impl std::error::Error for PgnError {}
+
+#[test]
+fn parse_pgn() {
+    let pgn = r#"
+[Event "Casual game"]
+[Site "?"]
+[White "Anderssen, Adolf"]
+[Black "Kieseritzky, Lionel"]
+[Result "1-0"]
+[Annotator "someone"]
+
+{The Immortal Game, more or less} 1. e4 e5 2. f4!? exf4 3. Bc4 Qh4+ $6 4. Kf1 b5
+(4... Nf6 {is the main line} (4... d6) 5. Nc3) ; a rest of line comment
+5. Bxb5 1-0
+
+% an escaped line
+[Event "from a position"]
+[SetUp "1"]
+[FEN "4k3/P7/8/8/8/8/8/4K3 w - - 0 40"]
+
+40. a8=Q+ Kd7 *
+
+1. d4 d5"#;
+    let games = parse(pgn).expect("PGN is invalid");
+    assert_eq!(games.len(), 3);
+
+    let game = &games[0];
+    assert_eq!(game.tag("White"), Some("Anderssen, Adolf"));
+    assert_eq!(game.tag("Annotator"), Some("someone"));
+    assert_eq!(game.result, GameResult::WhiteWins);
+    assert_eq!(game.mainline.comment.as_deref(), Some("The Immortal Game, more or less"));
+    let moves = &game.mainline.moves;
+    assert_eq!(moves.len(), 9);
+    assert_eq!(moves[2].nags, vec![5]);
+    assert_eq!(moves[5].nags, vec![6]);
+    assert_eq!(moves[7].comment.as_deref(), Some("a rest of line comment"));
+    assert_eq!(moves[7].variations.len(), 1);
+    let variation = &moves[7].variations[0];
+    assert_eq!(variation.moves.len(), 2);
+    assert_eq!(variation.moves[0].comment.as_deref(), Some("is the main line"));
+    assert_eq!(variation.moves[0].variations[0].moves[0].r#move, Move::from_string("d7d6", None).unwrap());
+    assert_eq!(game.final_position().unwrap().to_fen(), "rnb1kbnr/p1pp1ppp/8/1B6/4Pp1q/8/PPPP2PP/RNBQ1KNR b kq - 0 5");
+
+    assert_eq!(games[1].result, GameResult::Unknown);
+    assert_eq!(games[1].final_position().unwrap().to_fen(), "Q7/3k4/8/8/8/8/8/4K3 w - - 1 41");
+    assert_eq!(games[2].mainline.moves.len(), 2);
+    assert_eq!(games[2].result, GameResult::Unknown);
+
+    let en_passant = &parse("1. e4 Nf6 2. e5 d5 3. exd6 e.p. Nc6 4. d4 Nb8 5. d5 e5 6. dxe6e.p. *").expect("PGN is invalid")[0];
+    assert_eq!(en_passant.mainline.moves[4].r#move, Move::new(4, 4, 3, 5, Some(crate::chessboard::SpecialMove::EnPassant)));
+    assert_eq!(en_passant.final_position().unwrap().to_fen(), "rnbqkb1r/ppp2ppp/3PPn2/8/8/8/PPP2PPP/RNBQKBNR b KQkq - 0 6");
+
+    assert_eq!(parse("1. e4 e5 2. Ke3"), Err(PgnError::IllegalMove { line: 1, error: SanError::IllegalMove("Ke3".to_owned()) }));
+    assert_eq!(parse("1. e4 (1. d4 e5"), Err(PgnError::UnexpectedEnd { line: 1 }));
+    assert_eq!(parse("1. e4 {unclosed\n"), Err(PgnError::UnexpectedEnd { line: 2 }));
+    assert_eq!(parse("[Event \"a\"]\n\n1. e4 e5)"), Err(PgnError::UnexpectedToken { line: 3, token: ")".to_owned() }));
+    assert!(matches!(parse("[FEN \"8/8 w - - 0 1\"]\n*"), Err(PgnError::InvalidFen { line: 1, .. })));
+
+    // a game that can't be read is reported on its own, and the next ones are still read
+    let pgn = "1. e4 e5 1-0\n1. e5 d5 0-1\n1. d4 (1. e4 1-0) d5 *\n1. c4 (1. d4\n[Event \"a\"]\n1. Nf3 Nf6 1/2-1/2\n1. g3 {unclosed";
+    let games = parse_each(pgn);
+    let moves: Vec<Result<usize, PgnError>> = games.into_iter().map(|game| game.map(|game| game.mainline.moves.len())).collect();
+    assert_eq!(moves, [
+        Ok(2),
+        Err(PgnError::IllegalMove { line: 2, error: SanError::IllegalMove("e5".to_owned()) }),
+        Err(PgnError::UnexpectedToken { line: 3, token: "1-0".to_owned() }),
+        Err(PgnError::UnexpectedToken { line: 5, token: "[".to_owned() }),
+        Ok(2),
+        Err(PgnError::UnexpectedEnd { line: 7 })
+    ]);
+}
+
+#[test]
+fn write_pgn() {
+    let pgn = "[Event \"a \\\"quoted\\\" event\"]\n[SetUp \"1\"]\n[FEN \"r3k3/8/8/8/8/8/8/4K3 b q - 0 12\"]\n\n12... O-O-O {castling long, written with a comment long enough to need to be wrapped over two lines} 13. Kf2 (13. Ke2 $2 Rd2+) 13... Rd7 0-1";
+    let game = &parse(pgn).expect("PGN is invalid")[0];
+    let expected = "[Event \"a \\\"quoted\\\" event\"]
+[Site \"?\"]
+[Date \"????.??.??\"]
+[Round \"?\"]
+[White \"?\"]
+[Black \"?\"]
+[Result \"0-1\"]
+[SetUp \"1\"]
+[FEN \"r3k3/8/8/8/8/8/8/4K3 b q - 0 12\"]
+
+12... O-O-O {castling long, written with a comment long enough to need to be
+wrapped over two lines} 13. Kf2 (13. Ke2 $2 Rd2+) 13... Rd7 0-1
+
+";
+    assert_eq!(game.to_pgn(), expected);
+    let reread = &parse(&game.to_pgn()).expect("written PGN is invalid")[0];
+    assert_eq!(reread.mainline, game.mainline);
+    assert_eq!(reread.to_pgn(), expected);
+}
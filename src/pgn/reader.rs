@@ -0,0 +1,326 @@
+use crate::chessboard::Chessboard;
+
+use super::{Game, GameResult, MoveNode, PgnError, Variation};
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+enum Token {
+    TagStart,
+    TagEnd,
+    String(String),
+    /** a move, move number, tag name or result */
+    Symbol(String),
+    Period,
+    Asterisk,
+    Comment(String),
+    Nag(u8),
+    VariationStart,
+    VariationEnd
+}
+
+/** the NAG each move suffix annotation stands for */
+fn suffix_nag(suffix: &str) -> Option<u8> {
+    match suffix {
+        "!" => Some(1),
+        "?" => Some(2),
+        "!!" => Some(3),
+        "??" => Some(4),
+        "!?" => Some(5),
+        "?!" => Some(6),
+        _ => None
+    }
+}
+
+/** splits the text into tokens along with the line they start at, keeping the ones before an error */
+fn tokenize(pgn: &str, tokens: &mut Vec<(Token, usize)>) -> Result<(), PgnError> {
+    let mut chars = pgn.chars().peekable();
+    let mut line = 1;
+    let mut at_line_start = true;
+
+    while let Some(character) = chars.next() {
+        let token_line = line;
+        // lines starting with % are escaped from PGN, so they are skipped entirely
+        if character == '%' && at_line_start {
+            for character in chars.by_ref() {
+                if character == '\n' { break; }
+            }
+            line += 1;
+            continue;
+        }
+        at_line_start = character == '\n';
+
+        let token = match character {
+            '\n' => { line += 1; continue; }
+            _ if character.is_whitespace() => continue,
+            '[' => Token::TagStart,
+            ']' => Token::TagEnd,
+            '(' => Token::VariationStart,
+            ')' => Token::VariationEnd,
+            '.' => Token::Period,
+            '*' => Token::Asterisk,
+            '"' => {
+                let mut string = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(escaped) => string.push(escaped),
+                            None => return Err(PgnError::UnexpectedEnd { line })
+                        },
+                        Some('\n') | None => return Err(PgnError::UnexpectedEnd { line }),
+                        Some(character) => string.push(character)
+                    }
+                }
+                Token::String(string)
+            }
+            '{' => {
+                let mut comment = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(character) => {
+                            if character == '\n' { line += 1; }
+                            comment.push(character);
+                        }
+                        None => return Err(PgnError::UnexpectedEnd { line })
+                    }
+                }
+                Token::Comment(comment.split_whitespace().collect::<Vec<_>>().join(" "))
+            }
+            ';' => {
+                let comment: String = chars.by_ref().take_while(|&character| character != '\n').collect();
+                line += 1;
+                at_line_start = true;
+                Token::Comment(comment.trim().to_owned())
+            }
+            '$' => {
+                let mut digits = String::new();
+                while let Some(&digit) = chars.peek().filter(|character| character.is_ascii_digit()) {
+                    digits.push(digit);
+                    chars.next();
+                }
+                Token::Nag(digits.parse().map_err(|_| PgnError::UnexpectedToken { line, token: format!("${digits}") })?)
+            }
+            '!' | '?' => {
+                let mut suffix = character.to_string();
+                while let Some(&character) = chars.peek().filter(|&&character| matches!(character, '!' | '?')) {
+                    suffix.push(character);
+                    chars.next();
+                }
+                Token::Nag(suffix_nag(&suffix).ok_or(PgnError::UnexpectedToken { line, token: suffix })?)
+            }
+            _ if character.is_ascii_alphanumeric() => {
+                let mut symbol = character.to_string();
+                while let Some(&character) = chars.peek().filter(|&&character| character.is_ascii_alphanumeric() || "_+#=:-/".contains(character)) {
+                    symbol.push(character);
+                    chars.next();
+                }
+                // the periods of an "e.p." suffix would be read as tokens of their own, so the suffix is dropped here
+                let mut lookahead = chars.clone();
+                if symbol.ends_with('e') && lookahead.by_ref().take(3).eq(".p.".chars()) {
+                    chars = lookahead;
+                    symbol.pop();
+                    if symbol.is_empty() { continue; }
+                }
+                Token::Symbol(symbol)
+            }
+            _ => return Err(PgnError::UnexpectedToken { line, token: character.to_string() })
+        };
+        tokens.push((token, token_line));
+    }
+    Ok(())
+}
+
+fn is_result(token: &Token) -> bool {
+    match token {
+        Token::Asterisk => true,
+        Token::Symbol(symbol) => GameResult::from_string(symbol).is_some(),
+        _ => false
+    }
+}
+
+fn token_text(token: &Token) -> String {
+    match token {
+        Token::TagStart => "[".to_owned(),
+        Token::TagEnd => "]".to_owned(),
+        Token::String(string) => format!("\"{string}\""),
+        Token::Symbol(symbol) => symbol.clone(),
+        Token::Period => ".".to_owned(),
+        Token::Asterisk => "*".to_owned(),
+        Token::Comment(comment) => format!("{{{comment}}}"),
+        Token::Nag(nag) => format!("${nag}"),
+        Token::VariationStart => "(".to_owned(),
+        Token::VariationEnd => ")".to_owned()
+    }
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<(Token, usize)> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+    /** the line of the last token, for errors at the end of the text */
+    fn last_line(&self) -> usize {
+        self.tokens.last().map_or(1, |(_, line)| *line)
+    }
+    fn expect_next(&mut self) -> Result<(Token, usize), PgnError> {
+        self.next().ok_or(PgnError::UnexpectedEnd { line: self.last_line() })
+    }
+
+    /** reads the next game, or None if there is nothing but whitespace left */
+    fn parse_game(&mut self) -> Result<Option<Game>, PgnError> {
+        let Some((_, first_line)) = self.tokens.get(self.position).cloned() else { return Ok(None) };
+        let mut game = Game::default();
+
+        while let Some((Token::TagStart, _)) = self.tokens.get(self.position) {
+            self.position += 1;
+            let (name, value) = match (self.expect_next()?, self.expect_next()?, self.expect_next()?) {
+                ((Token::Symbol(name), _), (Token::String(value), _), (Token::TagEnd, _)) => (name, value),
+                ((Token::Symbol(_), _), (Token::String(_), _), (token, line)) |
+                ((Token::Symbol(_), _), (token, line), _) |
+                ((token, line), _, _) => return Err(PgnError::UnexpectedToken { line, token: token_text(&token) })
+            };
+            game.tags.push((name, value));
+        }
+
+        let mut chessboard = game.starting_position().map_err(|error| PgnError::InvalidFen { line: first_line, error })?;
+        let (mainline, result) = self.parse_variation(&mut chessboard, true)?;
+        game.mainline = mainline;
+        // if the movetext doesn't end with a result, the Result tag is the next best thing
+        game.result = result.or_else(|| game.tag("Result").and_then(GameResult::from_string)).unwrap_or_default();
+        Ok(Some(game))
+    }
+
+    /**
+        moves on to the next game after an error, which is after the next result or before the next tags that
+        follow some movetext, since the game can't be trusted to end where it should
+    */
+    fn skip_game(&mut self) {
+        // tags where they can't be, like after a variation that was never closed, are already the next game
+        if self.position.checked_sub(1).and_then(|position| self.tokens.get(position)).is_some_and(|(token, _)| *token == Token::TagStart) {
+            self.position -= 1;
+            return;
+        }
+        while let Some((token, _)) = self.tokens.get(self.position) {
+            let after_tags = self.position == 0 || matches!(self.tokens[self.position - 1].0, Token::TagEnd);
+            if *token == Token::TagStart && !after_tags { return; }
+            self.position += 1;
+            if is_result(token) { return; }
+        }
+    }
+
+    /**
+        reads moves until the end of the variation, or until the result if it is the main line. Every move
+        is checked against the chessboard, which is left as it was found
+    */
+    fn parse_variation(&mut self, chessboard: &mut Chessboard, is_mainline: bool) -> Result<(Variation, Option<GameResult>), PgnError> {
+        let mut variation = Variation::default();
+        let mut result = None;
+
+        loop {
+            let Some((token, line)) = self.next() else {
+                if is_mainline { break; }
+                return Err(PgnError::UnexpectedEnd { line: self.last_line() });
+            };
+            let unexpected = |token: &Token| PgnError::UnexpectedToken { line, token: token_text(token) };
+
+            match token {
+                Token::Symbol(ref symbol) if GameResult::from_string(symbol).is_some() => {
+                    if !is_mainline { return Err(unexpected(&token)); }
+                    result = GameResult::from_string(symbol);
+                    break;
+                }
+                Token::Asterisk => {
+                    if !is_mainline { return Err(unexpected(&token)); }
+                    result = Some(GameResult::Unknown);
+                    break;
+                }
+                // move numbers are only there for humans
+                Token::Symbol(symbol) if symbol.chars().all(|character| character.is_ascii_digit()) => (),
+                Token::Period => (),
+                Token::Symbol(san) => {
+                    let r#move = chessboard.parse_san(&san).map_err(|error| PgnError::IllegalMove { line, error })?;
+                    chessboard.make_move(r#move);
+                    variation.moves.push(MoveNode::new(r#move));
+                }
+                Token::Nag(nag) => match variation.moves.last_mut() {
+                    Some(node) => node.nags.push(nag),
+                    None => return Err(unexpected(&token))
+                },
+                Token::Comment(comment) => {
+                    let old_comment = match variation.moves.last_mut() {
+                        Some(node) => &mut node.comment,
+                        None => &mut variation.comment
+                    };
+                    *old_comment = Some(match old_comment.take() {
+                        Some(old_comment) => format!("{old_comment} {comment}"),
+                        None => comment
+                    });
+                }
+                Token::VariationStart => {
+                    // the variation replaces the last move, so it starts from the position before it
+                    let Some(node) = variation.moves.last_mut() else { return Err(unexpected(&token)) };
+                    chessboard.unmake_move();
+                    let (alternative, _) = self.parse_variation(chessboard, false)?;
+                    chessboard.make_move(node.r#move);
+                    node.variations.push(alternative);
+                }
+                Token::VariationEnd if !is_mainline => break,
+                // a game without a result is followed right away by the tags of the next one
+                Token::TagStart if is_mainline => {
+                    self.position -= 1;
+                    break;
+                }
+                Token::VariationEnd | Token::TagStart | Token::TagEnd | Token::String(_) => return Err(unexpected(&token))
+            }
+        }
+
+        for _ in &variation.moves { chessboard.unmake_move(); }
+        Ok((variation, result))
+    }
+}
+
+/** reads every game of a PGN file, checking that all the moves, including the ones in variations, are legal */
+pub fn parse(pgn: &str) -> Result<Vec<Game>, PgnError> {
+    let mut tokens = vec![];
+    tokenize(pgn, &mut tokens)?;
+    let mut parser = Parser { tokens, position: 0 };
+    let mut games = vec![];
+    while let Some(game) = parser.parse_game()? {
+        games.push(game);
+    }
+    Ok(games)
+}
+
+/**
+    like parse, but a game that can't be read doesn't stop the others from being read, so there is a result for
+    every game. Text that can't even be split into tokens ends the file, with its error as the last result
+*/
+pub fn parse_each(pgn: &str) -> Vec<Result<Game, PgnError>> {
+    let mut tokens = vec![];
+    let tokenize_error = tokenize(pgn, &mut tokens).err();
+    let mut parser = Parser { tokens, position: 0 };
+    let mut games = vec![];
+    loop {
+        match parser.parse_game() {
+            Ok(Some(game)) => {
+                // a game running into the text that couldn't be split isn't whole, the error is all there is to say
+                let cut_off = parser.position >= parser.tokens.len() && !parser.tokens.last().is_some_and(|(token, _)| is_result(token));
+                if tokenize_error.is_some() && cut_off { break; }
+                games.push(Ok(game));
+            }
+            Ok(None) => break,
+            Err(error) => {
+                games.push(Err(error));
+                parser.skip_game();
+            }
+        }
+    }
+    games.extend(tokenize_error.map(Err));
+    games
+}
@@ -0,0 +1,113 @@
+use std::fmt::{Display, Formatter, Error};
+
+use crate::chessboard::Chessboard;
+use crate::pieces::Color;
+
+use super::{Game, Variation, SEVEN_TAG_ROSTER};
+
+/** export format lines can't be longer than this */
+const MAX_LINE_LENGTH: usize = 80;
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/** comments are split into words so long ones can be broken between lines */
+fn push_comment(tokens: &mut Vec<String>, comment: &str) {
+    let words: Vec<&str> = comment.split_whitespace().collect();
+    match words[..] {
+        [] => tokens.push("{}".to_owned()),
+        [word] => tokens.push(format!("{{{word}}}")),
+        [first, ref middle @ .., last] => {
+            tokens.push(format!("{{{first}"));
+            tokens.extend(middle.iter().map(|word| word.to_string()));
+            tokens.push(format!("{last}}}"));
+        }
+    }
+}
+
+/** writes the moves of the variation, which starts at the position of the chessboard, and leaves it untouched */
+fn push_variation(tokens: &mut Vec<String>, chessboard: &mut Chessboard, variation: &Variation) {
+    if let Some(comment) = &variation.comment { push_comment(tokens, comment); }
+
+    // black moves only get a number at the start and after something interrupts the moves
+    let mut needs_number = true;
+    for node in &variation.moves {
+        if chessboard.to_play == Color::White {
+            tokens.push(format!("{}.", chessboard.move_number));
+        } else if needs_number {
+            tokens.push(format!("{}...", chessboard.move_number));
+        }
+        tokens.push(chessboard.move_to_san(node.r#move));
+        tokens.extend(node.nags.iter().map(|nag| format!("${nag}")));
+        needs_number = false;
+
+        if let Some(comment) = &node.comment {
+            push_comment(tokens, comment);
+            needs_number = true;
+        }
+        for alternative in &node.variations {
+            tokens.push("(".to_owned());
+            push_variation(tokens, chessboard, alternative);
+            tokens.push(")".to_owned());
+            needs_number = true;
+        }
+        chessboard.make_move(node.r#move);
+    }
+    for _ in &variation.moves { chessboard.unmake_move(); }
+}
+
+impl Game {
+    /**
+        writes the game in PGN export format: the seven tag roster (with "?" for the missing ones) followed
+        by the other tags, then the movetext wrapped at 80 columns. Panics if the FEN tag is invalid or
+        the moves aren't legal, which can't happen to games read by pgn::parse
+    */
+    pub fn to_pgn(&self) -> String {
+        let mut pgn = String::new();
+
+        for name in SEVEN_TAG_ROSTER {
+            let value = match name {
+                "Result" => self.result.to_string(),
+                "Date" => self.tag(name).unwrap_or("????.??.??").to_owned(),
+                _ => self.tag(name).unwrap_or("?").to_owned()
+            };
+            pgn.push_str(&format!("[{name} \"{}\"]\n", escape(&value)));
+        }
+        for (name, value) in &self.tags {
+            if SEVEN_TAG_ROSTER.contains(&name.as_str()) { continue; }
+            pgn.push_str(&format!("[{name} \"{}\"]\n", escape(value)));
+        }
+        pgn.push('\n');
+
+        let mut tokens = vec![];
+        let mut chessboard = self.starting_position().expect("the FEN tag is invalid");
+        push_variation(&mut tokens, &mut chessboard, &self.mainline);
+        tokens.push(self.result.to_string());
+
+        let mut line = String::new();
+        let mut previous: &str = "";
+        for token in &tokens {
+            // no spaces inside the parentheses of variations
+            let separator = if line.is_empty() || previous == "(" || token == ")" { "" } else { " " };
+            if !line.is_empty() && line.len() + separator.len() + token.len() > MAX_LINE_LENGTH {
+                pgn.push_str(&line);
+                pgn.push('\n');
+                line.clear();
+            } else {
+                line.push_str(separator);
+            }
+            line.push_str(token);
+            previous = token;
+        }
+        pgn.push_str(&line);
+        pgn.push_str("\n\n");
+        pgn
+    }
+}
+
+impl Display for Game {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "{}", self.to_pgn())
+    }
+}
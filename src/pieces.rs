@@ -9,8 +9,12 @@ mod bishop;
 mod queen;
 mod king;
 
-#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub use pawn::PROMOTION_PIECES;
+pub(crate) use king::castling_move;
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
 pub enum Color {
+    #[default]
     White,
     Black
 }
@@ -25,11 +29,6 @@ impl Color {
         if self == Color::White { Color::Black } else { Color::White }
     }
 }
-impl Default for Color {
-    fn default() -> Self {
-        Color::White
-    }
-}
 
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub enum PieceType {
@@ -121,7 +120,7 @@ impl Piece {
             PieceType::Queen => queen::append_legal_moves(position, self.color, board, moves),
             PieceType::King => king::append_legal_moves(position, self.color, board, moves),
             PieceType::Knight => knight::append_legal_moves(position, self.color, board, moves),
-            _ => ()
+            PieceType::Pawn => pawn::append_legal_moves(position, self.color, board, moves)
         }
     }
     pub fn get_legal_moves(&self, position: Coordinate, board: &Chessboard) -> Vec<Move> {
@@ -136,7 +135,7 @@ impl Piece {
             PieceType::Queen => queen::is_legal_move(r#move, self.color, board),
             PieceType::King => king::is_legal_move(r#move, self.color, board),
             PieceType::Knight => knight::is_legal_move(r#move, self.color, board),
-            _ => false
+            PieceType::Pawn => pawn::is_legal_move(r#move, self.color, board)
         }
     }
 }
@@ -151,6 +150,8 @@ impl std::fmt::Display for Piece {
 
 
 // functionality for the pieces
+pub(crate) const KNIGHT_OFFSETS: [(i8, i8); 8] = [(1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2)];
+
 fn add_line_to_moves(position: Coordinate, step: (i8, i8), color: Color, board: &Chessboard, moves: &mut Vec<Move>) {
     debug_assert_ne!(step, (0, 0));
     macro_rules! add_move {
@@ -212,3 +213,4 @@ fn is_legal_move_in_linear_movement(r#move: Move, color: Color, board: &Chessboa
     }
     true
 }
+
@@ -1,10 +1,8 @@
-use crate::chessboard::{Coordinate, Chessboard, Move};
+use crate::chessboard::{Coordinate, Chessboard, Move, SpecialMove};
 
-use super::Color;
+use super::{Color, Piece, PieceType};
 
 pub fn append_legal_moves(position: Coordinate, color: Color, board: &Chessboard, moves: &mut Vec<Move>) {
-    println!("IMPLEMENT CASTLING IMPLEMENT CASTLING (crate::pieces::king::append_legal_moves)");
-
     for x in -1..=1 {
         for y in -1..=1 {
             if x == 0 && y == 0 { continue; }
@@ -15,8 +13,16 @@ pub fn append_legal_moves(position: Coordinate, color: Color, board: &Chessboard
             }
         }
     }
+    for kingside in [true, false] {
+        if let Some(r#move) = castling_move(position, color, kingside, board) {
+            moves.push(r#move);
+        }
+    }
 }
 pub fn is_legal_move(r#move: Move, color: Color, board: &Chessboard) -> bool {
+    if r#move.special_move == Some(SpecialMove::Castling) {
+        return [true, false].into_iter().any(|kingside| castling_move(r#move.from, color, kingside, board) == Some(r#move));
+    }
     if r#move.from.x.abs_diff(r#move.to.x) > 1 { return false; }
     if r#move.from.y.abs_diff(r#move.to.y) > 1 { return false; }
     if r#move.special_move.is_some() { return false; }
@@ -26,3 +32,56 @@ pub fn is_legal_move(r#move: Move, color: Color, board: &Chessboard) -> bool {
         Err(_) => false
     }
 }
+
+/**
+    returns the castling move of the king at the position if it is allowed: the player still has the right
+    to castle to that side, every square between the king and the rook is empty and the king is not in
+    check, doesn't pass through an attacked square and doesn't land on one
+*/
+pub(crate) fn castling_move(position: Coordinate, color: Color, kingside: bool, board: &Chessboard) -> Option<Move> {
+    let (y, rights_index) = match color { Color::White => (0, 0), Color::Black => (7, 2) };
+    let (rook_x, king_destination_x, rights_index) = if kingside { (7, 6, rights_index) } else { (0, 2, rights_index + 1) };
+
+    if !board.castling[rights_index] || position != Coordinate::new(4, y) { return None; }
+    if board.get(4, y) != Ok(Some(Piece::new(color, PieceType::King))) { return None; }
+    if board.get(rook_x, y) != Ok(Some(Piece::new(color, PieceType::Rook))) { return None; }
+
+    if (rook_x.min(4) + 1..rook_x.max(4)).any(|x| board.get(x, y) != Ok(None)) { return None; }
+    if (king_destination_x.min(4)..=king_destination_x.max(4)).any(|x| board.is_square_attacked(Coordinate::new(x, y), color.opposite())) {
+        return None;
+    }
+
+    Some(Move::new(4, y, king_destination_x, y, Some(SpecialMove::Castling)))
+}
+
+#[test]
+pub fn king_castling() {
+    let castling = |fen: &str| {
+        let chessboard = Chessboard::from_fen(fen).expect("FEN is invalid");
+        let king = Piece::new(chessboard.to_play, PieceType::King);
+        let y = if chessboard.to_play == Color::White { 0 } else { 7 };
+        king.get_legal_moves(Coordinate::new(4, y), &chessboard)
+            .into_iter()
+            .filter(|r#move| r#move.special_move == Some(SpecialMove::Castling))
+            .map(|r#move| r#move.to.x)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(castling("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1"), vec![6, 2]);
+    assert_eq!(castling("r3k2r/8/8/8/8/8/8/R3K2R b Kq - 0 1"), vec![2]);
+    // b1 is attacked, but the king doesn't pass through it
+    assert_eq!(castling("1r2k2r/8/8/8/8/8/8/R3K2R w KQ - 0 1"), vec![6, 2]);
+    // pieces in between
+    assert_eq!(castling("r3k2r/8/8/8/8/8/8/RN2K1NR w KQ - 0 1"), vec![]);
+    // in check
+    assert_eq!(castling("r3k2r/4r3/8/8/8/8/8/R3K2R w KQ - 0 1"), vec![]);
+    // passing through an attacked square
+    assert_eq!(castling("r3kr2/8/8/8/8/8/8/R3K2R w KQ - 0 1"), vec![2]);
+    // landing on an attacked square
+    assert_eq!(castling("r1r1k2r/8/8/8/8/8/8/R3K2R w KQ - 0 1"), vec![6]);
+
+    let chessboard = Chessboard::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w Q - 0 1").expect("FEN is invalid");
+    assert_eq!(chessboard.is_legal_move(Move::new(4, 0, 2, 0, Some(SpecialMove::Castling))), Ok(true));
+    assert_eq!(chessboard.is_legal_move(Move::new(4, 0, 6, 0, Some(SpecialMove::Castling))), Ok(false));
+    assert_eq!(chessboard.is_legal_move(Move::new(4, 0, 2, 0, None)), Ok(false));
+}
@@ -16,8 +16,8 @@ pub fn append_legal_moves(position: Coordinate, color: Color, board: &Chessboard
     }
     for y_sign in [-1, 1] {
         for x_sign in [-1, 1] {
-            add_offset!(1 * x_sign, 2 * y_sign);
-            add_offset!(2 * x_sign, 1 * y_sign);
+            add_offset!(x_sign, 2 * y_sign);
+            add_offset!(2 * x_sign, y_sign);
         }
     }
 }
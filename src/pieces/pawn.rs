@@ -1,21 +1,104 @@
-use crate::chessboard::{Coordinate, Chessboard, Move};
+use crate::chessboard::{Coordinate, Chessboard, Move, SpecialMove};
 
-use super::Color;
+use super::{Color, PieceType};
+
+/** the pieces a pawn can turn into, in the order the promotions are generated */
+pub const PROMOTION_PIECES: [PieceType; 4] = [PieceType::Queen, PieceType::Rook, PieceType::Bishop, PieceType::Knight];
+
+/** (direction of movement, rank the pawn starts at, rank the pawn promotes at, rank an en passant capture lands at) */
+fn ranks(color: Color) -> (i8, u8, u8, u8) {
+    match color {
+        Color::White => (1, 1, 7, 5),
+        Color::Black => (-1, 6, 0, 2)
+    }
+}
 
 pub fn append_legal_moves(position: Coordinate, color: Color, board: &Chessboard, moves: &mut Vec<Move>) {
-    let direction = match color { Color::White => 1, Color::Black => -1 };
+    let (direction, start_y, promotion_y, en_passant_y) = ranks(color);
     let next_y = (position.y as i8 + direction) as u8;
 
-    if let Ok(Some(piece)) = board.get(position.x, next_y) { } else {
-        moves.push(Move::new(position.x, position.y, position.x, next_y, None));
+    let mut add_move = |x: u8, y: u8, special_move: Option<SpecialMove>| {
+        if y == promotion_y {
+            for piece_type in PROMOTION_PIECES {
+                moves.push(Move::new(position.x, position.y, x, y, Some(SpecialMove::Promotion(piece_type))));
+            }
+        } else {
+            moves.push(Move::new(position.x, position.y, x, y, special_move));
+        }
+    };
+
+    if board.get(position.x, next_y) == Ok(None) {
+        add_move(position.x, next_y, None);
+        let two_squares_y = (next_y as i8 + direction) as u8;
+        if position.y == start_y && board.get(position.x, two_squares_y) == Ok(None) {
+            add_move(position.x, two_squares_y, Some(SpecialMove::TwoSquareAdvance));
+        }
     }
     for dx in [-1, 1] {
         let next_x = (position.x as i8 + dx) as u8;
-        if matches!(board.get(next_x, next_y), Ok(Some(piece)) if piece.color != color) {
-            moves.push(Move::new(position.x, position.y, (position.x as i8 + dx) as u8, next_y, None));
+        match board.get(next_x, next_y) {
+            Ok(Some(piece)) if piece.color != color => add_move(next_x, next_y, None),
+            Ok(None) if next_y == en_passant_y && board.en_passant_target() == Some(Coordinate::new(next_x, next_y)) => {
+                add_move(next_x, next_y, Some(SpecialMove::EnPassant));
+            }
+            _ => ()
         }
     }
 }
 
 pub fn is_legal_move(r#move: Move, color: Color, board: &Chessboard) -> bool {
+    let (direction, start_y, promotion_y, en_passant_y) = ranks(color);
+    let (from, to) = (r#move.from, r#move.to);
+    let dx = from.x.abs_diff(to.x);
+    let dy = to.y as i8 - from.y as i8;
+    let target = board.get(to.x, to.y);
+
+    let special_move = if dx == 0 && dy == direction {
+        if target != Ok(None) { return false; }
+        None
+    } else if dx == 0 && dy == 2 * direction {
+        if from.y != start_y || target != Ok(None) { return false; }
+        if board.get(from.x, (from.y as i8 + direction) as u8) != Ok(None) { return false; }
+        Some(SpecialMove::TwoSquareAdvance)
+    } else if dx == 1 && dy == direction {
+        match target {
+            Ok(Some(piece)) if piece.color != color => None,
+            Ok(None) if to.y == en_passant_y && board.en_passant_target() == Some(to) => Some(SpecialMove::EnPassant),
+            _ => return false
+        }
+    } else {
+        return false;
+    };
+
+    if to.y == promotion_y {
+        matches!(r#move.special_move, Some(SpecialMove::Promotion(piece_type)) if PROMOTION_PIECES.contains(&piece_type))
+    } else {
+        r#move.special_move == special_move
+    }
+}
+
+#[test]
+pub fn pawn_legal_moves() {
+    let chessboard = Chessboard::from_fen("4k3/1P6/8/3pP3/8/8/2P5/4K3 w - d6 0 1").expect("FEN is invalid");
+    let pawn = super::Piece::new(Color::White, PieceType::Pawn);
+
+    assert_eq!(pawn.get_legal_moves(Coordinate::new(2, 1), &chessboard), vec![
+        Move::new(2, 1, 2, 2, None),
+        Move::new(2, 1, 2, 3, Some(SpecialMove::TwoSquareAdvance)),
+    ]);
+    assert_eq!(pawn.get_legal_moves(Coordinate::new(4, 4), &chessboard), vec![
+        Move::new(4, 4, 4, 5, None),
+        Move::new(4, 4, 3, 5, Some(SpecialMove::EnPassant)),
+    ]);
+    assert_eq!(pawn.get_legal_moves(Coordinate::new(1, 6), &chessboard), PROMOTION_PIECES.map(|piece_type| {
+        Move::new(1, 6, 1, 7, Some(SpecialMove::Promotion(piece_type)))
+    }));
+
+    for r#move in pawn.get_legal_moves(Coordinate::new(4, 4), &chessboard) {
+        assert!(pawn.is_legal_move(r#move, &chessboard));
+    }
+    assert!(!pawn.is_legal_move(Move::new(4, 4, 3, 5, None), &chessboard));
+    assert!(!pawn.is_legal_move(Move::new(1, 6, 1, 7, None), &chessboard));
+    assert!(!pawn.is_legal_move(Move::new(1, 6, 1, 7, Some(SpecialMove::Promotion(PieceType::King))), &chessboard));
+    assert!(!pawn.is_legal_move(Move::new(2, 1, 2, 3, None), &chessboard));
 }
@@ -0,0 +1,395 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::chessboard::{Chessboard, Move};
+use crate::eval::evaluate;
+use crate::movepick::{is_tactical, Heuristics, MovePicker};
+use crate::syzygy::Tablebases;
+use crate::timeman::{Clock, TimeManager};
+use crate::tt::{score_from_table, Bound, TranspositionTable, DEFAULT_MEGABYTES};
+
+// negamax alpha-beta (https://www.chessprogramming.org/Alpha-Beta) with iterative deepening: the position is searched
+// at depth 1, 2, 3... until a limit is hit, and the best move of the last complete depth is played. After the first
+// move of a node the others are searched with a null window, only to prove they are worse (principal variation
+// search), which lets most nodes be cut off by the transposition table. At the end of the depth, a quiescence search
+// keeps playing captures until the position is quiet, so the evaluation isn't trusted in the middle of an exchange
+
+/** the score of checkmating right now, mates further away score one less for each ply they take */
+pub const MATE_SCORE: i32 = 30000;
+/** the deepest the search can go, in plies */
+pub const MAX_PLY: usize = 128;
+
+/** checks if the score means someone is getting mated */
+pub fn is_mate_score(score: i32) -> bool {
+    score.abs() >= MATE_SCORE - MAX_PLY as i32
+}
+
+/** the number of moves (not plies) until mate, negative if the player to play is the one getting mated */
+pub fn moves_to_mate(score: i32) -> Option<i32> {
+    if !is_mate_score(score) { return None; }
+    let plies = MATE_SCORE - score.abs();
+    Some(if score > 0 { (plies + 1) / 2 } else { -plies / 2 })
+}
+
+/** when to stop searching, a search without limits only ends when stopped from the outside */
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    pub depth: Option<u32>,
+    pub nodes: Option<u64>,
+    /** a fixed time to think for */
+    pub time: Option<Duration>,
+    /** the clock of the player to play, how long to think is decided by the time manager */
+    pub clock: Option<Clock>
+}
+
+/** what a search found after completing a depth */
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SearchResult {
+    /** None only if there are no legal moves */
+    pub best_move: Option<Move>,
+    /** in centipawns from the point of view of the player to play, see MATE_SCORE for mates */
+    pub score: i32,
+    pub depth: u32,
+    /** the moves both players are expected to play, starting with the best move */
+    pub principal_variation: Vec<Move>,
+    pub nodes: u64,
+    pub time: Duration,
+    /** how full the transposition table is, in permille */
+    pub hashfull: usize
+}
+
+pub struct Search {
+    limits: Limits,
+    stop: Arc<AtomicBool>,
+    start: Instant,
+    /** only there when searching with a clock */
+    time_manager: Option<TimeManager>,
+    nodes: u64,
+    /** pv[ply] is the best line found from the node at that ply, pv[0] being the principal variation */
+    pv: Vec<Vec<Move>>,
+    /** the principal variation of the previous depth, its moves are searched first */
+    previous_pv: Vec<Move>,
+    /** kept from one search to the next, so a new search starts from what the previous ones found */
+    table: TranspositionTable,
+    heuristics: Heuristics,
+    tablebases: Option<Arc<Tablebases>>,
+    /** the moves of the root the tablebases keep, empty when the root isn't in them */
+    root_moves: Vec<Move>
+}
+
+impl Search {
+    pub fn new(limits: Limits) -> Self {
+        Search {
+            limits,
+            stop: Arc::new(AtomicBool::new(false)),
+            start: Instant::now(),
+            time_manager: None,
+            nodes: 0,
+            pv: vec![vec![]; MAX_PLY + 1],
+            previous_pv: vec![],
+            table: TranspositionTable::new(DEFAULT_MEGABYTES),
+            heuristics: Heuristics::new(),
+            tablebases: None,
+            root_moves: vec![]
+        }
+    }
+
+    /** the limits of the next run, which also starts over if it was stopped */
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+        self.stop.store(false, Ordering::Relaxed);
+    }
+
+    /** replaces the transposition table by an empty one of about that many megabytes */
+    pub fn resize_table(&mut self, megabytes: usize) {
+        self.table = TranspositionTable::new(megabytes);
+    }
+    /** forgets every position searched before, the next search won't be from the same game */
+    pub fn new_game(&mut self) {
+        self.table.clear();
+        self.heuristics.clear();
+    }
+    /** the tablebases the root moves are filtered with, when the root is in them */
+    pub fn set_tablebases(&mut self, tablebases: Option<Arc<Tablebases>>) {
+        self.tablebases = tablebases;
+    }
+    /** how full the transposition table is, in permille */
+    pub fn hashfull(&self) -> usize {
+        self.table.hashfull()
+    }
+
+    /** setting the flag to true makes the search return as soon as possible, even from another thread */
+    pub fn stop_flag(&self) -> Arc<AtomicBool> {
+        self.stop.clone()
+    }
+
+    fn should_stop(&mut self) -> bool {
+        if self.stop.load(Ordering::Relaxed) { return true; }
+        let out_of_nodes = self.limits.nodes.is_some_and(|nodes| self.nodes >= nodes);
+        let elapsed = self.start.elapsed();
+        let out_of_time = self.limits.time.is_some_and(|time| elapsed >= time)
+            || self.time_manager.as_ref().is_some_and(|time_manager| elapsed >= time_manager.hard_limit());
+        if out_of_nodes || out_of_time { self.stop.store(true, Ordering::Relaxed); }
+        out_of_nodes || out_of_time
+    }
+
+    /**
+        searches the position deeper and deeper until a limit is hit, calling report after every complete depth,
+        and returns the result of the last complete one. The chessboard is left as it was found
+    */
+    pub fn run(&mut self, chessboard: &mut Chessboard, mut report: impl FnMut(&SearchResult)) -> SearchResult {
+        self.start = Instant::now();
+        self.time_manager = self.limits.clock.map(|clock| TimeManager::new(clock, chessboard.move_number));
+        self.nodes = 0;
+        self.previous_pv.clear();
+        self.table.new_search();
+        self.heuristics.new_search();
+
+        // the search still picks between the moves that keep the result, the tables don't know which one is best
+        self.root_moves = self.tablebases.as_ref().and_then(|tablebases| tablebases.filter_root_moves(chessboard).ok()).unwrap_or_default();
+        let first_move = self.root_moves.first().copied().or_else(|| chessboard.get_legal_moves().first().copied());
+        let mut result = SearchResult { best_move: first_move, ..SearchResult::default() };
+        let max_depth = self.limits.depth.unwrap_or(MAX_PLY as u32).min(MAX_PLY as u32);
+
+        for depth in 1..=max_depth {
+            let score = self.negamax(chessboard, depth, 0, -MATE_SCORE, MATE_SCORE);
+            // a depth that wasn't searched completely can't be trusted
+            if self.stop.load(Ordering::Relaxed) { break; }
+
+            self.previous_pv = self.pv[0].clone();
+            result = SearchResult {
+                best_move: self.pv[0].first().copied(),
+                score,
+                depth,
+                principal_variation: self.pv[0].clone(),
+                nodes: self.nodes,
+                time: self.start.elapsed(),
+                hashfull: self.hashfull()
+            };
+            report(&result);
+
+            // nothing changes by searching deeper if there are no moves or the mate was already found
+            if result.best_move.is_none() || moves_to_mate(score).is_some_and(|moves| moves.unsigned_abs() <= depth.div_ceil(2)) { break; }
+            if self.should_stop() { break; }
+            if let Some(time_manager) = &mut self.time_manager {
+                time_manager.depth_completed(result.best_move);
+                if self.start.elapsed() >= time_manager.soft_limit() { break; }
+            }
+        }
+        result.nodes = self.nodes;
+        result.time = self.start.elapsed();
+        result.hashfull = self.hashfull();
+        result
+    }
+
+    fn negamax(&mut self, chessboard: &mut Chessboard, depth: u32, ply: usize, mut alpha: i32, mut beta: i32) -> i32 {
+        self.nodes += 1;
+        self.pv[ply].clear();
+        if self.nodes.is_multiple_of(1024) && self.should_stop() { return 0; }
+        if self.stop.load(Ordering::Relaxed) { return 0; }
+
+        if ply > 0 {
+            // a repetition is scored as a draw right away, if it was good the player could have done better anyway
+            if chessboard.repetitions() >= 2 || chessboard.claimable_draw().is_some() || chessboard.has_insufficient_material() { return 0; }
+
+            // mate distance pruning: no score in this node can beat a mate that was already found closer to the root
+            alpha = alpha.max(ply as i32 - MATE_SCORE);
+            beta = beta.min(MATE_SCORE - ply as i32 - 1);
+            if alpha >= beta { return alpha; }
+        }
+
+        let mut moves = chessboard.get_legal_moves();
+        if moves.is_empty() { return if chessboard.is_in_check() { ply as i32 - MATE_SCORE } else { 0 }; }
+        if ply == 0 && !self.root_moves.is_empty() { moves.retain(|r#move| self.root_moves.contains(r#move)); }
+        if ply >= MAX_PLY { return evaluate(chessboard); }
+        if depth == 0 { return self.quiescence(chessboard, ply, alpha, beta); }
+
+        let hash = chessboard.hash();
+        let entry = self.table.probe(hash);
+        // nodes with a null window aren't on the principal variation, so cutting them off doesn't lose any of it
+        let is_pv_node = beta - alpha > 1;
+        if let Some(entry) = entry.filter(|entry| !is_pv_node && ply > 0 && entry.depth as u32 >= depth) {
+            let score = score_from_table(entry.score, ply);
+            match entry.bound {
+                Bound::Exact => return score,
+                Bound::Lower if score >= beta => return score,
+                Bound::Upper if score <= alpha => return score,
+                _ => ()
+            }
+        }
+
+        let hash_move = entry.and_then(|entry| entry.best_move).or_else(|| self.previous_pv.get(ply).copied());
+        let picker = MovePicker::new(chessboard, moves, hash_move, ply, &self.heuristics);
+
+        let original_alpha = alpha;
+        let mut best_score = -MATE_SCORE;
+        let mut best_move = None;
+        for (index, r#move) in picker.enumerate() {
+            let is_quiet = !is_tactical(chessboard, r#move);
+            chessboard.make_move(r#move);
+            let mut score;
+            if index == 0 {
+                score = -self.negamax(chessboard, depth - 1, ply + 1, -beta, -alpha);
+            } else {
+                score = -self.negamax(chessboard, depth - 1, ply + 1, -alpha - 1, -alpha);
+                // the move turned out to be better, so its real score is needed
+                if score > alpha && score < beta {
+                    score = -self.negamax(chessboard, depth - 1, ply + 1, -beta, -alpha);
+                }
+            }
+            chessboard.unmake_move();
+            if self.stop.load(Ordering::Relaxed) { return 0; }
+
+            if score > best_score {
+                best_score = score;
+                best_move = Some(r#move);
+                if score > alpha {
+                    alpha = score;
+                    let (current, deeper) = self.pv.split_at_mut(ply + 1);
+                    current[ply].clear();
+                    current[ply].push(r#move);
+                    current[ply].extend_from_slice(&deeper[0]);
+                }
+                if score >= beta {
+                    if is_quiet { self.heuristics.record_cutoff(chessboard.to_play, r#move, ply, depth); }
+                    break;
+                }
+            }
+        }
+
+        let bound = if best_score >= beta {
+            Bound::Lower
+        } else if best_score > original_alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        self.table.store(hash, depth as u8, bound, best_score, best_move, ply);
+        best_score
+    }
+
+    /**
+        searches captures and promotions that don't lose material until none are left. The player to play can
+        always stop capturing, so the evaluation of the position is a lower bound (stand pat), except in check,
+        where every move is searched to see mates
+    */
+    fn quiescence(&mut self, chessboard: &mut Chessboard, ply: usize, mut alpha: i32, beta: i32) -> i32 {
+        self.nodes += 1;
+        self.pv[ply].clear();
+        if self.nodes.is_multiple_of(1024) && self.should_stop() { return 0; }
+        if self.stop.load(Ordering::Relaxed) { return 0; }
+
+        let moves = chessboard.get_legal_moves();
+        let in_check = chessboard.is_in_check();
+        if moves.is_empty() { return if in_check { ply as i32 - MATE_SCORE } else { 0 }; }
+        if chessboard.has_insufficient_material() { return 0; }
+        if ply >= MAX_PLY { return evaluate(chessboard); }
+
+        let mut best_score = -MATE_SCORE;
+        if !in_check {
+            best_score = evaluate(chessboard);
+            if best_score >= beta { return best_score; }
+            alpha = alpha.max(best_score);
+        }
+
+        let moves = if in_check {
+            moves
+        } else {
+            moves.into_iter().filter(|&r#move| is_tactical(chessboard, r#move) && chessboard.see(r#move) >= 0).collect()
+        };
+        for r#move in MovePicker::new(chessboard, moves, None, ply, &self.heuristics) {
+            chessboard.make_move(r#move);
+            let score = -self.quiescence(chessboard, ply + 1, -beta, -alpha);
+            chessboard.unmake_move();
+            if self.stop.load(Ordering::Relaxed) { return 0; }
+
+            if score > best_score {
+                best_score = score;
+                if score > alpha { alpha = score; }
+                if score >= beta { break; }
+            }
+        }
+        best_score
+    }
+}
+
+#[cfg(test)]
+fn search(fen: &str, limits: Limits) -> SearchResult {
+    let mut chessboard = Chessboard::from_fen(fen).expect("FEN is invalid");
+    let result = Search::new(limits).run(&mut chessboard, |_| ());
+    assert_eq!(chessboard.to_fen(), fen, "the search didn't restore the position");
+    result
+}
+
+#[test]
+fn finds_mates() {
+    let depth = |depth| Limits { depth: Some(depth), ..Limits::default() };
+
+    let result = search("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", depth(4));
+    assert_eq!(result.best_move, Move::from_string("a1a8", None));
+    assert_eq!(result.score, MATE_SCORE - 1);
+    assert_eq!(moves_to_mate(result.score), Some(1));
+    // mate in 2: 1. Kb6 Kb8 2. Rh8#
+    let result = search("k7/8/2K5/8/8/8/8/7R w - - 0 1", depth(5));
+    assert_eq!(moves_to_mate(result.score), Some(2));
+    assert_eq!(result.principal_variation.len(), 3);
+    // black is the one getting mated, Kb8 is forced and then Rh8#
+    let result = search("k7/8/1K6/8/8/8/8/7R b - - 0 1", depth(3));
+    assert_eq!(moves_to_mate(result.score), Some(-1));
+
+    let result = search("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", depth(3));
+    assert_eq!((result.best_move, result.score), (None, 0));
+}
+
+#[test]
+fn search_limits_and_pv() {
+    let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+    let result = search(start, Limits { nodes: Some(5000), ..Limits::default() });
+    assert!(result.nodes < 5000 + 1024 && result.best_move.is_some());
+    assert!((1..=1000).contains(&result.hashfull));
+    let result = search(start, Limits { time: Some(Duration::from_millis(50)), ..Limits::default() });
+    assert!(result.time < Duration::from_millis(500) && result.best_move.is_some());
+    // two seconds left for the rest of the game don't allow more than a fraction of a second for the first move
+    let clock = Clock { time: Duration::from_secs(2), increment: Duration::ZERO, moves_to_go: None };
+    let result = search(start, Limits { clock: Some(clock), ..Limits::default() });
+    assert!(result.time < Duration::from_millis(500) && result.best_move.is_some());
+
+    // the free queen has to be taken, and the principal variation has to be made of legal moves
+    let fen = "4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1";
+    let result = search(fen, Limits { depth: Some(3), ..Limits::default() });
+    assert_eq!(result.depth, 3);
+    assert_eq!(result.best_move, Move::from_string("d2d5", None));
+    let mut chessboard = Chessboard::from_fen(fen).unwrap();
+    for &r#move in &result.principal_variation {
+        assert_eq!(chessboard.is_legal_move(r#move), Ok(true));
+        chessboard.make_move(r#move);
+    }
+}
+
+#[test]
+fn transposition_table_is_reused() {
+    let mut chessboard = Chessboard::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+    let mut search = Search::new(Limits { depth: Some(3), ..Limits::default() });
+    let first = search.run(&mut chessboard, |_| ());
+    let second = search.run(&mut chessboard, |_| ());
+    assert!(second.nodes < first.nodes / 2, "{} nodes the second time, {} the first", second.nodes, first.nodes);
+    assert_eq!((second.best_move, second.score), (first.best_move, first.score));
+
+    search.new_game();
+    assert_eq!(search.run(&mut chessboard, |_| ()).nodes, first.nodes);
+}
+
+#[test]
+fn quiescence_sees_recaptures() {
+    // at depth 1 the queen would grab the pawn if the search didn't see exd5
+    let result = search("4k3/8/4p3/3p4/8/8/8/3QK3 w - - 0 1", Limits { depth: Some(1), ..Limits::default() });
+    assert_ne!(result.best_move, Move::from_string("d1d5", None));
+    // but a pawn that isn't defended is taken
+    let result = search("4k3/8/8/3p4/8/8/8/3QK3 w - - 0 1", Limits { depth: Some(1), ..Limits::default() });
+    assert_eq!(result.best_move, Move::from_string("d1d5", None));
+
+    // taking the knight only leaves a bishop that can't mate, which is a draw and not a bishop up for white
+    let mut chessboard = Chessboard::from_fen("8/8/8/8/8/2k5/2N5/K1B5 b - - 0 1").expect("FEN is invalid");
+    assert_eq!(Search::new(Limits::default()).quiescence(&mut chessboard, 0, -MATE_SCORE, MATE_SCORE), 0);
+}
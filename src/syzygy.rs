@@ -0,0 +1,530 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter, Error};
+use std::ops::Neg;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::chessboard::{Chessboard, Move};
+use crate::movepick::is_capture;
+use crate::pieces::{Color, PieceType};
+
+mod table;
+
+use table::{piece_code, Table};
+
+// Syzygy tablebases (https://www.chessprogramming.org/Syzygy_Bases) know the result of every position with few
+// pieces. The WDL tables (.rtbw) tell if the player to play wins, draws or loses, and the DTZ tables (.rtbz) how many
+// plies are left until the next capture or pawn move on the way there, which is enough to play the right moves
+// without ever running into the 50 move rule. Tables don't store everything: positions with en passant or winning
+// captures are found by trying the captures, and DTZ tables only store one side to move, so the other side is
+// found one ply deeper. Tables with castling rights don't exist
+
+/** the most pieces, kings included, a table can have */
+pub const MAX_PIECES: usize = 7;
+
+/** the number of pieces of each kind, indexed by the piece codes of the files */
+type Material = [u8; 16];
+
+/**
+    the result of the position for the player to play. Cursed wins and blessed losses are wins and losses that
+    end up as draws by the 50 move rule
+*/
+#[derive(Debug, PartialEq, Eq, Clone, Copy, PartialOrd, Ord)]
+pub enum Wdl {
+    Loss = -2,
+    BlessedLoss = -1,
+    Draw = 0,
+    CursedWin = 1,
+    Win = 2
+}
+
+impl Wdl {
+    fn from_value(value: i32) -> Option<Self> {
+        match value {
+            -2 => Some(Wdl::Loss),
+            -1 => Some(Wdl::BlessedLoss),
+            0 => Some(Wdl::Draw),
+            1 => Some(Wdl::CursedWin),
+            2 => Some(Wdl::Win),
+            _ => None
+        }
+    }
+    /** 1 for wins, -1 for losses, 0 for draws */
+    pub fn signum(self) -> i32 {
+        (self as i32).signum()
+    }
+}
+
+impl Neg for Wdl {
+    type Output = Wdl;
+    fn neg(self) -> Wdl {
+        Wdl::from_value(-(self as i32)).expect("the opposite of a result is a result")
+    }
+}
+
+/** why a position couldn't be probed */
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ProbeError {
+    /** the position has castling rights or too many pieces to be in the tables */
+    NotInTables,
+    /** the name of the file the position needs, which isn't in the directory */
+    MissingTable(String),
+    /** the name of a file that couldn't be read or isn't a valid table */
+    InvalidTable(String)
+}
+
+impl Display for ProbeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match self {
+            ProbeError::NotInTables => write!(f, "the position can't be in the tables"),
+            ProbeError::MissingTable(name) => write!(f, "{name} is missing"),
+            ProbeError::InvalidTable(name) => write!(f, "{name} isn't a valid table")
+        }
+    }
+}
+
+impl std::error::Error for ProbeError {}
+
+/** a root move ranked by the tables, see Tablebases::rank_root_moves */
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct RankedMove {
+    pub r#move: Move,
+    /** the result of the position after the move for the player who makes it */
+    pub wdl: Wdl,
+    /** plies from the root to the next capture or pawn move, negative if losing, 0 if unknown or drawn */
+    pub dtz: i32,
+    /** the higher the better */
+    pub rank: i32
+}
+
+/** the rank of sure wins, more than any DTZ, so the wins the 50 move rule may turn into draws rank below */
+const MAX_RANK: i32 = 1 << 18;
+
+fn material(chessboard: &Chessboard) -> Material {
+    let mut material = [0; 16];
+    for color in [Color::White, Color::Black] {
+        for piece_type in [PieceType::Pawn, PieceType::Knight, PieceType::Bishop, PieceType::Rook, PieceType::Queen, PieceType::King] {
+            material[piece_code(piece_type, color) as usize] = chessboard.pieces(piece_type, color).count_ones() as u8;
+        }
+    }
+    material
+}
+
+fn swap_colors(material: Material) -> Material {
+    let mut swapped = [0; 16];
+    swapped[..8].copy_from_slice(&material[8..]);
+    swapped[8..].copy_from_slice(&material[..8]);
+    swapped
+}
+
+/** the pieces of one side as the tables write them, strongest first, like KRP */
+fn side_name(material: &Material, color: Color) -> String {
+    "KQRBNP".chars().map(|character| {
+        let piece_type = PieceType::from_char(character).expect("all of them are pieces");
+        character.to_string().repeat(material[piece_code(piece_type, color) as usize] as usize)
+    }).collect()
+}
+
+fn material_from_name(name: &str) -> Option<Material> {
+    let (white, black) = name.split_once('v')?;
+    let mut material = [0; 16];
+    for (side, color) in [(white, Color::White), (black, Color::Black)] {
+        if side.matches('K').count() != 1 { return None; }
+        for character in side.chars() {
+            let piece_type = PieceType::from_char(character).filter(|_| character.is_ascii_uppercase())?;
+            material[piece_code(piece_type, color) as usize] += 1;
+        }
+    }
+    Some(material)
+}
+
+/**
+    the name of the table holding the position, like KRPvKR: the pieces of the stronger side first, the side with
+    more pieces, or with the stronger pieces when both have as many
+*/
+pub fn table_name(chessboard: &Chessboard) -> String {
+    let material = material(chessboard);
+    material_name(&side_name(&material, Color::White), &side_name(&material, Color::Black))
+}
+
+/** the name of the ending between the two sides, each one written in KQRBNP order, the stronger one first */
+pub(crate) fn material_name(white: &str, black: &str) -> String {
+    let strength = |name: &str| (name.len(), name.chars().map(|character| 6 - "KQRBNP".find(character).unwrap_or(6)).collect::<Vec<usize>>());
+    if strength(white) >= strength(black) { format!("{white}v{black}") } else { format!("{black}v{white}") }
+}
+
+/** the DTZ of the move that is a capture or pawn move, from the result after it */
+fn dtz_before_zeroing(wdl: Wdl) -> i32 {
+    match wdl {
+        Wdl::Win => 1,
+        Wdl::CursedWin => 101,
+        Wdl::BlessedLoss => -101,
+        Wdl::Loss => -1,
+        Wdl::Draw => 0
+    }
+}
+
+fn is_zeroing(chessboard: &Chessboard, r#move: Move) -> bool {
+    is_capture(chessboard, r#move) || chessboard.get(r#move.from.x, r#move.from.y).is_ok_and(|piece| piece.is_some_and(|piece| piece.piece_type == PieceType::Pawn))
+}
+
+/** the tables found in a directory, each one is only read the first time it is needed */
+pub struct Tablebases {
+    /** indexed by file name */
+    tables: HashMap<String, (PathBuf, OnceLock<Option<Table>>)>,
+    max_pieces: usize
+}
+
+impl Tablebases {
+    /** looks for the .rtbw and .rtbz files of the directory, without reading them yet */
+    pub fn new(directory: impl AsRef<Path>) -> std::io::Result<Self> {
+        let mut tables = HashMap::new();
+        let mut max_pieces = 0;
+        for entry in std::fs::read_dir(directory)? {
+            let path = entry?.path();
+            let Some(name) = path.file_name().and_then(|name| name.to_str()).map(str::to_owned) else { continue };
+            let Some((material_name, "rtbw" | "rtbz")) = name.rsplit_once('.') else { continue };
+            let Some(material) = material_from_name(material_name) else { continue };
+
+            max_pieces = max_pieces.max(material.iter().map(|&count| count as usize).sum());
+            tables.insert(name, (path, OnceLock::new()));
+        }
+        Ok(Tablebases { tables, max_pieces: max_pieces.min(MAX_PIECES) })
+    }
+
+    /** the number of table files found */
+    pub fn len(&self) -> usize {
+        self.tables.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.tables.is_empty()
+    }
+    /** the most pieces of the tables found, positions with more can't be probed */
+    pub fn max_pieces(&self) -> usize {
+        self.max_pieces
+    }
+
+    /** checks if the position could be in the tables, ignoring which files are actually there */
+    pub fn can_probe(&self, chessboard: &Chessboard) -> bool {
+        chessboard.occupancy().count_ones() as usize <= self.max_pieces && chessboard.castling.iter().all(|can_castle| !can_castle)
+    }
+
+    fn probe_table(&self, chessboard: &Chessboard, is_dtz: bool, wdl: Wdl) -> Result<Option<i32>, ProbeError> {
+        let material = material(chessboard);
+        // the only table that isn't stored
+        if chessboard.occupancy().count_ones() == 2 { return Ok(Some(0)); }
+
+        let name = format!("{}.{}", table_name(chessboard), if is_dtz { "rtbz" } else { "rtbw" });
+        let (path, table) = self.tables.get(&name).ok_or_else(|| ProbeError::MissingTable(name.clone()))?;
+        let table = table.get_or_init(|| {
+            let table_material = material_from_name(name.split_once('.')?.0)?;
+            Table::new(std::fs::read(path).ok()?, table_material, is_dtz)
+        });
+        let table = table.as_ref().ok_or_else(|| ProbeError::InvalidTable(name.clone()))?;
+        table.probe(chessboard, material, wdl).ok_or(ProbeError::InvalidTable(name))
+    }
+
+    /**
+        the result of the position from the WDL table, checked against the captures (and pawn moves if asked),
+        since the tables may store anything for positions where one of them is the best move. The flag is true
+        if the best move is one of those, which the DTZ table doesn't know about
+    */
+    fn search(&self, chessboard: &mut Chessboard, check_zeroing_moves: bool) -> Result<(Wdl, bool), ProbeError> {
+        let moves = chessboard.get_legal_moves();
+        let mut searched = 0;
+        let mut best = Wdl::Loss;
+        for &r#move in &moves {
+            let capture = is_capture(chessboard, r#move);
+            let pawn_move = !capture && is_zeroing(chessboard, r#move);
+            if !(capture || check_zeroing_moves && pawn_move) { continue; }
+
+            searched += 1;
+            chessboard.make_move(r#move);
+            let result = self.search(chessboard, false);
+            chessboard.unmake_move();
+            let wdl = -result?.0;
+            if wdl > best {
+                best = wdl;
+                if wdl == Wdl::Win { return Ok((wdl, true)); }
+            }
+        }
+
+        // when all the moves were searched, the table isn't needed, and may be wrong because of en passant
+        let no_more_moves = searched > 0 && searched == moves.len();
+        let wdl = if no_more_moves {
+            best
+        } else {
+            let value = self.probe_table(chessboard, false, Wdl::Draw)?.ok_or(ProbeError::NotInTables)?;
+            Wdl::from_value(value).ok_or_else(|| ProbeError::InvalidTable(table_name(chessboard)))?
+        };
+        if best >= wdl { return Ok((best, best > Wdl::Draw || no_more_moves)); }
+        Ok((wdl, false))
+    }
+
+    /** if the player to play wins, draws or loses with perfect play */
+    pub fn probe_wdl(&self, chessboard: &Chessboard) -> Result<Wdl, ProbeError> {
+        if !self.can_probe(chessboard) { return Err(ProbeError::NotInTables); }
+        Ok(self.search(&mut chessboard.clone(), false)?.0)
+    }
+
+    /**
+        the number of plies to the next capture or pawn move with perfect play, positive if the player to play wins,
+        negative if they lose, 0 if it is a draw. Cursed wins and blessed losses count 100 plies more
+    */
+    pub fn probe_dtz(&self, chessboard: &Chessboard) -> Result<i32, ProbeError> {
+        if !self.can_probe(chessboard) { return Err(ProbeError::NotInTables); }
+        self.dtz(&mut chessboard.clone())
+    }
+
+    fn dtz(&self, chessboard: &mut Chessboard) -> Result<i32, ProbeError> {
+        let (wdl, zeroing_best_move) = self.search(chessboard, true)?;
+        // draws aren't stored
+        if wdl == Wdl::Draw { return Ok(0); }
+        if zeroing_best_move { return Ok(dtz_before_zeroing(wdl)); }
+
+        if let Some(dtz) = self.probe_table(chessboard, true, wdl)? {
+            let cursed = matches!(wdl, Wdl::CursedWin | Wdl::BlessedLoss);
+            return Ok((dtz + 100 * cursed as i32) * wdl.signum());
+        }
+
+        // the table stores the other side to move, so the best move is found one ply deeper
+        let mut best = i32::MAX;
+        for r#move in chessboard.get_legal_moves() {
+            let zeroing = is_zeroing(chessboard, r#move);
+            chessboard.make_move(r#move);
+            // for zeroing moves the DTZ is the one before the move, only the result after it is needed
+            let result = if zeroing {
+                self.search(chessboard, false).map(|(wdl, _)| -dtz_before_zeroing(wdl))
+            } else {
+                self.dtz(chessboard).map(|dtz| -dtz)
+            };
+            let mates = chessboard.is_in_check() && chessboard.get_legal_moves().is_empty();
+            chessboard.unmake_move();
+
+            let mut dtz = result?;
+            if dtz == 1 && mates { best = 1; }
+            if !zeroing { dtz += dtz.signum(); }
+            if dtz < best && dtz.signum() == wdl.signum() { best = dtz; }
+        }
+        // without legal moves it is mate
+        Ok(if best == i32::MAX { -1 } else { best })
+    }
+
+    /**
+        ranks the moves of the position by the tables: wins are ranked highest when they are sure to win before
+        the 50 move rule, then the closer to zeroing the better, and losses the other way around. Only the WDL
+        tables are needed, but without the DTZ ones all the wins and all the losses get the same rank
+    */
+    pub fn rank_root_moves(&self, chessboard: &Chessboard) -> Result<Vec<RankedMove>, ProbeError> {
+        if !self.can_probe(chessboard) { return Err(ProbeError::NotInTables); }
+        self.rank_by_dtz(chessboard).or_else(|_| self.rank_by_wdl(chessboard))
+    }
+
+    fn rank_by_dtz(&self, chessboard: &Chessboard) -> Result<Vec<RankedMove>, ProbeError> {
+        let mut chessboard = chessboard.clone();
+        let halfmove_clock = chessboard.halfmove_clock() as i32;
+        // like in the reference code, a repetition anywhere since the last zeroing move counts, not only of the root
+        let repeated = chessboard.has_repeated();
+
+        let mut ranked = vec![];
+        for r#move in chessboard.get_legal_moves() {
+            chessboard.make_move(r#move);
+            let (wdl, mut dtz) = if chessboard.halfmove_clock() == 0 {
+                let wdl = -self.search(&mut chessboard, false)?.0;
+                (wdl, dtz_before_zeroing(wdl))
+            } else if chessboard.claimable_draw().is_some() {
+                (Wdl::Draw, 0)
+            } else {
+                let dtz = -self.dtz(&mut chessboard)?;
+                let wdl = -self.search(&mut chessboard, false)?.0;
+                (wdl, dtz + dtz.signum())
+            };
+            // mating right away is the shortest win there is
+            if dtz == 2 && chessboard.is_in_check() && chessboard.get_legal_moves().is_empty() { dtz = 1; }
+            chessboard.unmake_move();
+
+            // the 50 move rule counts from the last zeroing move, which may be before the root
+            let rank = if dtz > 0 {
+                if dtz + halfmove_clock <= 99 && !repeated { MAX_RANK } else { MAX_RANK - (dtz + halfmove_clock) }
+            } else if dtz < 0 {
+                if -dtz * 2 + halfmove_clock < 100 { -MAX_RANK } else { -MAX_RANK + (-dtz + halfmove_clock) }
+            } else {
+                0
+            };
+            ranked.push(RankedMove { r#move, wdl, dtz, rank });
+        }
+        ranked.sort_by_key(|ranked_move| std::cmp::Reverse(ranked_move.rank));
+        Ok(ranked)
+    }
+
+    fn rank_by_wdl(&self, chessboard: &Chessboard) -> Result<Vec<RankedMove>, ProbeError> {
+        let mut chessboard = chessboard.clone();
+        let mut ranked = vec![];
+        for r#move in chessboard.get_legal_moves() {
+            chessboard.make_move(r#move);
+            let wdl = if chessboard.claimable_draw().is_some() { Ok(Wdl::Draw) } else { self.search(&mut chessboard, false).map(|(wdl, _)| -wdl) };
+            chessboard.unmake_move();
+
+            let wdl = wdl?;
+            let rank = match wdl {
+                Wdl::Win => MAX_RANK,
+                Wdl::CursedWin => MAX_RANK - 101,
+                Wdl::Draw => 0,
+                Wdl::BlessedLoss => -MAX_RANK + 101,
+                Wdl::Loss => -MAX_RANK
+            };
+            ranked.push(RankedMove { r#move, wdl, dtz: 0, rank });
+        }
+        ranked.sort_by_key(|ranked_move| std::cmp::Reverse(ranked_move.rank));
+        Ok(ranked)
+    }
+
+    /** the root moves that keep the best result the tables know of, for the search to choose from */
+    pub fn filter_root_moves(&self, chessboard: &Chessboard) -> Result<Vec<Move>, ProbeError> {
+        let ranked = self.rank_root_moves(chessboard)?;
+        let best = ranked.first().map_or(0, |ranked_move| ranked_move.rank);
+        Ok(ranked.into_iter().take_while(|ranked_move| ranked_move.rank == best).map(|ranked_move| ranked_move.r#move).collect())
+    }
+}
+
+#[test]
+fn table_names() {
+    let name = |fen| table_name(&Chessboard::from_fen(fen).unwrap());
+    assert_eq!(name("8/8/8/4k3/8/8/8/3QK3 w - - 0 1"), "KQvK");
+    assert_eq!(name("3qk3/8/8/8/8/8/8/4K3 w - - 0 1"), "KQvK");
+    assert_eq!(name("3rk3/8/8/8/8/8/4P3/3RK3 w - - 0 1"), "KRPvKR");
+    // with as many pieces, the strongest pieces come first
+    assert_eq!(name("3rk3/8/8/8/8/8/8/3BK3 w - - 0 1"), "KRvKB");
+    assert_eq!(name("3qk3/8/8/8/8/8/8/2RRK3 b - - 0 1"), "KRRvKQ");
+    assert_eq!(name("2nbk3/8/8/8/8/8/8/2NNK3 b - - 0 1"), "KBNvKNN");
+    assert_eq!(name("4k3/8/8/8/8/8/8/4K3 w - - 0 1"), "KvK");
+}
+
+#[test]
+fn probe_tables() {
+    let directory = std::env::temp_dir().join(format!("rust-chess-syzygy-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    // tables where every position has the same value: KQvK is won by the side with the queen, with 9 plies to
+    // zeroing, which the DTZ table stores as 4 moves. The pieces are written in the order K, Q, K on both sides
+    let table = |magic: [u8; 4], parts: &[u8]| {
+        let mut data = magic.to_vec();
+        data.extend([table::SPLIT, 0, 0x66, 0x55, 0xEE, 0]);
+        data.extend(parts);
+        data.resize(16, 0);
+        data
+    };
+    std::fs::write(directory.join("KQvK.rtbw"), table(table::WDL_MAGIC, &[0x80, 4, 0x80, 0])).unwrap();
+    std::fs::write(directory.join("KBvK.rtbw"), [0; 16]).unwrap();
+    std::fs::write(directory.join("notes.txt"), "not a table").unwrap();
+    let wdl_only = Tablebases::new(&directory).unwrap();
+    std::fs::write(directory.join("KQvK.rtbz"), table(table::DTZ_MAGIC, &[0x80, 4])).unwrap();
+    let tablebases = Tablebases::new(&directory).unwrap();
+    assert_eq!((wdl_only.len(), tablebases.len(), tablebases.max_pieces()), (2, 3, 3));
+
+    let chessboard = |fen| Chessboard::from_fen(fen).unwrap();
+    let white_queen = chessboard("8/8/8/4k3/8/8/8/3QK3 w - - 0 1");
+    let black_queen = chessboard("3qk3/8/8/8/8/8/8/4K3 b - - 0 1");
+    assert_eq!(tablebases.probe_wdl(&white_queen), Ok(Wdl::Win));
+    assert_eq!(tablebases.probe_wdl(&chessboard("8/8/8/4k3/8/8/8/3QK3 b - - 0 1")), Ok(Wdl::Loss));
+    assert_eq!(tablebases.probe_wdl(&black_queen), Ok(Wdl::Win));
+    assert_eq!(tablebases.probe_dtz(&white_queen), Ok(9));
+    assert_eq!(tablebases.probe_dtz(&black_queen), Ok(9));
+    // the DTZ table only stores the side with the queen to move, the other side is one ply further
+    assert_eq!(tablebases.probe_dtz(&chessboard("8/8/8/4k3/8/8/8/3QK3 b - - 0 1")), Ok(-10));
+    // the capture is found without the table
+    assert_eq!(tablebases.probe_wdl(&chessboard("8/8/8/8/8/8/3k4/3Q3K b - - 0 1")), Ok(Wdl::Draw));
+    assert_eq!(tablebases.probe_wdl(&chessboard("4k3/8/8/8/8/8/8/4K3 w - - 0 1")), Ok(Wdl::Draw));
+
+    assert_eq!(tablebases.probe_wdl(&chessboard("4k3/8/8/8/8/8/8/3RK3 w - - 0 1")), Err(ProbeError::MissingTable("KRvK.rtbw".to_string())));
+    assert_eq!(tablebases.probe_wdl(&chessboard("4k3/8/8/8/8/8/8/3BK3 w - - 0 1")), Err(ProbeError::InvalidTable("KBvK.rtbw".to_string())));
+    assert_eq!(tablebases.probe_wdl(&chessboard("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1")), Err(ProbeError::NotInTables));
+    assert_eq!(tablebases.probe_wdl(&chessboard("4k3/8/8/8/8/8/8/2QQK3 w - - 0 1")), Err(ProbeError::NotInTables));
+
+    // every move keeps the win but the ones leaving the queen to be taken
+    for tablebases in [&tablebases, &wdl_only] {
+        let ranked = tablebases.rank_root_moves(&white_queen).unwrap();
+        let rank = |r#move: &str| ranked.iter().find(|ranked| ranked.r#move == white_queen.parse_uci_move(r#move).unwrap()).unwrap().rank;
+        assert_eq!((rank("d1d2"), rank("d1d4"), rank("e1f2")), (MAX_RANK, 0, MAX_RANK));
+        let moves = tablebases.filter_root_moves(&white_queen).unwrap();
+        assert!(moves.contains(&white_queen.parse_uci_move("d1d2").unwrap()));
+        assert!(!moves.contains(&white_queen.parse_uci_move("d1d4").unwrap()));
+    }
+    let ranked = tablebases.rank_root_moves(&white_queen).unwrap();
+    assert!(ranked.iter().all(|ranked| ranked.rank != MAX_RANK || (ranked.wdl, ranked.dtz) == (Wdl::Win, 11)));
+
+    // without enough moves left before the 50 move rule, the win isn't sure anymore
+    let late = chessboard("8/8/8/4k3/8/8/8/3QK3 w - - 95 80");
+    let ranked = tablebases.rank_root_moves(&late).unwrap();
+    assert_eq!(ranked[0].rank, MAX_RANK - 11 - 95);
+
+    // broken tables with pawns, where every file has the same order and pieces, and every part a single value
+    let pawn_table = |flags: u8, file: &[u8], parts: usize| {
+        let mut data = table::WDL_MAGIC.to_vec();
+        data.push(flags);
+        for _ in 0..4 { data.extend(file); }
+        data.resize(data.len() + (data.len() & 1), 0);
+        for _ in 0..4 * parts { data.extend([0x80, 4]); }
+        data.resize(80, 0);
+        data
+    };
+    // every piece is a white pawn
+    std::fs::write(directory.join("KPPPPPvK.rtbw"), pawn_table(table::SPLIT | 2, &[0, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11], 2)).unwrap();
+    // the white king comes before the black pawn, so the pawn is counted among the squares the king can't be on
+    std::fs::write(directory.join("KPvKP.rtbw"), pawn_table(2, &[0, 1, 0x11, 0x66, 0x99, 0xEE], 1)).unwrap();
+    let tablebases = Tablebases::new(&directory).unwrap();
+    assert_eq!(tablebases.probe_wdl(&chessboard("4k3/8/8/8/8/8/PPPPP3/4K3 w - - 0 1")), Err(ProbeError::InvalidTable("KPPPPPvK.rtbw".to_string())));
+    assert_eq!(tablebases.probe_wdl(&chessboard("4k3/4p3/8/8/8/8/4P3/4K3 w - - 0 1")), Err(ProbeError::InvalidTable("KPvKP.rtbw".to_string())));
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+// the real tables are too big to be part of the crate, so this only runs with the directory of the 3 piece ones:
+// SYZYGY_PATH=<directory> cargo test probe_real_tables -- --ignored
+#[test]
+#[ignore]
+fn probe_real_tables() {
+    use crate::dtm::{Dtm, DtmTables};
+
+    let directory = std::env::var("SYZYGY_PATH").expect("SYZYGY_PATH has to be the directory of the 3 piece tables");
+    let tablebases = Tablebases::new(directory).unwrap();
+    let chessboard = |fen| Chessboard::from_fen(fen).unwrap();
+    // with the king on the sixth rank in front of its pawn, white wins whoever is to play
+    assert_eq!(tablebases.probe_wdl(&chessboard("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1")), Ok(Wdl::Win));
+    assert_eq!(tablebases.probe_wdl(&chessboard("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1")), Ok(Wdl::Loss));
+    assert_eq!(tablebases.probe_wdl(&chessboard("4k3/4P3/4K3/8/8/8/8/8 b - - 0 1")), Ok(Wdl::Draw));
+    assert_eq!(tablebases.probe_wdl(&chessboard("8/8/8/8/8/8/8/kBK5 w - - 0 1")), Ok(Wdl::Draw));
+
+    // the distance to mate tables are made independently, by retrograde analysis. Without anything to capture, the
+    // only zeroing move of KQvK and KRvK is the mate, so DTZ is the distance to mate, give or take the ply some
+    // Syzygy values are rounded by
+    let mut dtm_tables = DtmTables::new();
+    for (name, piece) in [("KQvK", 4), ("KRvK", 1), ("KPvK", 0)] {
+        dtm_tables.generate(name).unwrap();
+        for index in (0..64 * 64 * 64u32).step_by(5) {
+            let squares = [index % 64, index / 64 % 64, index / 4096].map(|square| square as u8);
+            if squares[0] == squares[1] || squares[0] == squares[2] || squares[1] == squares[2] { continue; }
+            if piece == 0 && !(8..56).contains(&squares[1]) { continue; }
+            for to_play in [Color::White, Color::Black] {
+                let mut chessboard = Chessboard::default();
+                for (square, code) in squares.into_iter().zip([5, piece, 11]) { chessboard.set_code(square % 8, square / 8, code); }
+                chessboard.to_play = to_play;
+                // positions that can't happen, like the player not to play being in check, have no distance to mate
+                let Some(dtm) = dtm_tables.probe(&chessboard) else { continue };
+                let fen = chessboard.to_fen();
+
+                let wdl = match dtm { Dtm::Win(_) => Wdl::Win, Dtm::Draw => Wdl::Draw, Dtm::Loss(_) => Wdl::Loss };
+                assert_eq!(tablebases.probe_wdl(&chessboard), Ok(wdl), "{fen}");
+                let dtz = tablebases.probe_dtz(&chessboard).unwrap();
+                match dtm {
+                    Dtm::Draw => assert_eq!(dtz, 0, "{fen}"),
+                    // there is nothing to count for the player who is already mated
+                    Dtm::Loss(0) => (),
+                    Dtm::Win(plies) | Dtm::Loss(plies) if piece != 0 => {
+                        assert!(dtz.signum() == wdl.signum() && dtz.unsigned_abs().abs_diff(plies as u32) <= 1, "{fen}: {dtz} {dtm:?}");
+                    }
+                    _ => assert_eq!(dtz.signum(), wdl.signum(), "{fen}")
+                }
+            }
+        }
+    }
+}
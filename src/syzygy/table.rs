@@ -0,0 +1,748 @@
+use crate::bitboard;
+use crate::chessboard::Chessboard;
+use crate::pieces::{Color, PieceType};
+
+use super::{Material, Wdl, MAX_PIECES};
+
+// the layout of the files and the way positions are turned into indices follow the probing code Ronald de Man
+// published along with the tables (https://github.com/syzygy1/tb), as found in Stockfish and Fathom
+
+pub(super) const WDL_MAGIC: [u8; 4] = [0x71, 0xE8, 0x23, 0x5D];
+pub(super) const DTZ_MAGIC: [u8; 4] = [0xD7, 0x66, 0x0C, 0xA5];
+
+/** the flags of a table, in its first byte */
+pub(super) const SPLIT: u8 = 1;
+const HAS_PAWNS: u8 = 2;
+
+/** the flags of each part of a table */
+const STM: u8 = 1;
+const MAPPED: u8 = 2;
+const WIN_PLIES: u8 = 4;
+const LOSS_PLIES: u8 = 8;
+const WIDE: u8 = 16;
+const SINGLE_VALUE: u8 = 128;
+
+/** a square below the a1-h8 diagonal is negative, above it positive */
+const fn off_diagonal(square: u8) -> i32 {
+    (square >> 3) as i32 - (square & 7) as i32
+}
+
+const TRIANGLE: [u8; 10] = [0, 1, 2, 3, 9, 10, 11, 18, 19, 27];
+
+/** the lookup tables that turn squares into indices, the same for every table */
+struct Maps {
+    /** a2-h7 to 47..0, the leading pawn is the one with the highest value */
+    pawns: [u64; 64],
+    /** the squares below the a1-h8 diagonal to 0..27 */
+    b1h1h7: [u64; 64],
+    /** the a1-d1-d4 triangle to 0..9, the squares on the diagonal last */
+    a1d1d4: [u64; 64],
+    /** the 462 ways to place two kings with the first one in the a1-d1-d4 triangle, indexed by a1d1d4 of the first */
+    kk: [[u64; 64]; 10],
+    /** binomial[k][n] is the number of ways to choose k out of n */
+    binomial: [[u64; 64]; MAX_PIECES + 1],
+    /** indexed by the number of leading pawns and the square of the first one */
+    lead_pawn_index: [[u64; 64]; MAX_PIECES],
+    /** indexed by the number of leading pawns and the file (a to d) */
+    lead_pawns_size: [[u64; 4]; MAX_PIECES]
+}
+
+const fn generate_maps() -> Maps {
+    let mut maps = Maps {
+        pawns: [0; 64],
+        b1h1h7: [0; 64],
+        a1d1d4: [0; 64],
+        kk: [[0; 64]; 10],
+        binomial: [[0; 64]; MAX_PIECES + 1],
+        lead_pawn_index: [[0; 64]; MAX_PIECES],
+        lead_pawns_size: [[0; 4]; MAX_PIECES]
+    };
+
+    let mut code = 0;
+    let mut square = 0;
+    while square < 64 {
+        if off_diagonal(square) < 0 {
+            maps.b1h1h7[square as usize] = code;
+            code += 1;
+        }
+        square += 1;
+    }
+
+    code = 0;
+    let mut i = 0;
+    while i < TRIANGLE.len() {
+        if off_diagonal(TRIANGLE[i]) < 0 {
+            maps.a1d1d4[TRIANGLE[i] as usize] = code;
+            code += 1;
+        }
+        i += 1;
+    }
+    i = 0;
+    while i < TRIANGLE.len() {
+        if off_diagonal(TRIANGLE[i]) == 0 {
+            maps.a1d1d4[TRIANGLE[i] as usize] = code;
+            code += 1;
+        }
+        i += 1;
+    }
+
+    // both kings on the diagonal come last
+    code = 0;
+    let mut both_on_diagonal = [(0, 0); 64];
+    let mut both_on_diagonal_count = 0;
+    let mut index = 0;
+    while index < 10 {
+        let mut i = 0;
+        while i < TRIANGLE.len() {
+            let first = TRIANGLE[i];
+            i += 1;
+            if maps.a1d1d4[first as usize] != index as u64 { continue; }
+
+            let mut second = 0;
+            while second < 64 {
+                let (dx, dy) = ((first & 7) as i32 - (second & 7) as i32, (first >> 3) as i32 - (second >> 3) as i32);
+                let touching = dx.abs() <= 1 && dy.abs() <= 1;
+                if touching || (off_diagonal(first) == 0 && off_diagonal(second) > 0) {
+                    // illegal, or the mirror of another position
+                } else if off_diagonal(first) == 0 && off_diagonal(second) == 0 {
+                    both_on_diagonal[both_on_diagonal_count] = (index, second);
+                    both_on_diagonal_count += 1;
+                } else {
+                    maps.kk[index][second as usize] = code;
+                    code += 1;
+                }
+                second += 1;
+            }
+        }
+        index += 1;
+    }
+    i = 0;
+    while i < both_on_diagonal_count {
+        let (index, second) = both_on_diagonal[i];
+        maps.kk[index][second as usize] = code;
+        code += 1;
+        i += 1;
+    }
+
+    maps.binomial[0][0] = 1;
+    let mut n = 1;
+    while n < 64 {
+        let mut k = 0;
+        while k <= MAX_PIECES && k <= n {
+            maps.binomial[k][n] = if k > 0 { maps.binomial[k - 1][n - 1] } else { 0 } + if k < n { maps.binomial[k][n - 1] } else { 0 };
+            k += 1;
+        }
+        n += 1;
+    }
+
+    // the files are encoded separately, so the index starts over at every file
+    let mut available = 48;
+    let mut count = 1;
+    while count < MAX_PIECES {
+        let mut file = 0;
+        while file < 4 {
+            let mut index = 0;
+            let mut rank = 1;
+            while rank < 7 {
+                let square = file + 8 * rank;
+                if count == 1 {
+                    available -= 2;
+                    maps.pawns[square] = available + 1;
+                    maps.pawns[square ^ 7] = available;
+                }
+                maps.lead_pawn_index[count][square] = index;
+                index += maps.binomial[count - 1][maps.pawns[square] as usize];
+                rank += 1;
+            }
+            maps.lead_pawns_size[count][file] = index;
+            file += 1;
+        }
+        count += 1;
+    }
+    maps
+}
+
+static MAPS: Maps = generate_maps();
+
+/** the piece codes used in the files: 1 to 6 for white pawn, knight, bishop, rook, queen and king, 9 to 14 for black */
+pub(super) fn piece_code(piece_type: PieceType, color: Color) -> u8 {
+    let code = match piece_type {
+        PieceType::Pawn => 1,
+        PieceType::Knight => 2,
+        PieceType::Bishop => 3,
+        PieceType::Rook => 4,
+        PieceType::Queen => 5,
+        PieceType::King => 6
+    };
+    if color == Color::Black { code + 8 } else { code }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(offset..offset + 2)?.try_into().ok()?))
+}
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+fn read_u32_be(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+fn read_u64_be(data: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(data.get(offset..offset + 8)?.try_into().ok()?))
+}
+
+/**
+    one compressed part of a table: the positions with one side to move, and with the leading pawn on one file.
+    Values are compressed with recursive pairing (pairs of symbols become new symbols) and then Huffman coding,
+    and the offsets point into the data of the table
+*/
+#[derive(Debug, Default, Clone)]
+struct PairsData {
+    flags: u8,
+    /** the piece codes in the order they are encoded */
+    pieces: [u8; MAX_PIECES],
+    /** how many pieces are encoded together, ending with a 0 */
+    group_len: [usize; MAX_PIECES + 1],
+    /** what the index of each group is multiplied by, the last one being the number of positions */
+    group_index: [u64; MAX_PIECES + 1],
+    block_size: usize,
+    /** the sparse index has an entry every span positions */
+    span: u64,
+    sparse_index_size: usize,
+    block_count: usize,
+    block_length_size: usize,
+    /** also the value of every position if the part has a single value */
+    min_symbol_length: u8,
+    lowest_symbol: usize,
+    /** the smallest code of each length, left aligned */
+    base64: Vec<u64>,
+    /** how many values each symbol stands for, minus one */
+    symbol_length: Vec<u8>,
+    /** the two symbols each symbol is made of, 12 bits each */
+    btree: usize,
+    sparse_index: usize,
+    block_length: usize,
+    data: usize,
+    /** where the DTZ values of wins, losses, cursed wins and blessed losses are, for mapped DTZ tables */
+    map_index: [usize; 4]
+}
+
+pub(super) struct Table {
+    data: Vec<u8>,
+    is_dtz: bool,
+    /** the material with the first side of the name as white */
+    material: Material,
+    /** the material with the first side of the name as black */
+    swapped_material: Material,
+    piece_count: usize,
+    has_pawns: bool,
+    /** if a piece besides the kings is alone of its kind and color, so three pieces can be encoded together */
+    has_unique_pieces: bool,
+    /** the pawns of the leading color, which has the fewest pawns, then the pawns of the other one */
+    pawn_count: [usize; 2],
+    /** indexed by the side to move (only one for DTZ tables, which store one side) and the file of the leading pawn */
+    parts: Vec<Vec<PairsData>>
+}
+
+impl Table {
+    /** reads a table from the contents of its file, None if they don't make a valid table for that material */
+    pub(super) fn new(data: Vec<u8>, material: Material, is_dtz: bool) -> Option<Self> {
+        let magic = if is_dtz { DTZ_MAGIC } else { WDL_MAGIC };
+        if data.get(0..4)? != magic || data.len() % 64 != 16 { return None; }
+
+        let swapped_material = super::swap_colors(material);
+        let count = |code: u8| material[code as usize] as usize;
+        let piece_count = material.iter().map(|&count| count as usize).sum();
+        let has_pawns = count(1) + count(9) > 0;
+        let has_unique_pieces = (1..6).chain(9..14).any(|code| count(code) == 1);
+        // both sides having pawns, the one with fewer leads because it compresses better
+        let white_leads = count(9) == 0 || (count(1) > 0 && count(9) >= count(1));
+        let pawn_count = if white_leads { [count(1), count(9)] } else { [count(9), count(1)] };
+
+        let mut table = Table {
+            data: vec![],
+            is_dtz,
+            material,
+            swapped_material,
+            piece_count,
+            has_pawns,
+            has_unique_pieces,
+            pawn_count,
+            parts: vec![]
+        };
+        if !(2..=MAX_PIECES).contains(&piece_count) { return None; }
+        let flags = *data.get(4)?;
+        if (flags & HAS_PAWNS != 0) != has_pawns || (flags & SPLIT != 0) != (material != swapped_material) { return None; }
+
+        let sides = if !is_dtz && material != swapped_material { 2 } else { 1 };
+        let files = if has_pawns { 4 } else { 1 };
+        table.parts = vec![vec![PairsData::default(); files]; sides];
+        let both_have_pawns = has_pawns && pawn_count[1] > 0;
+
+        let mut position = 5;
+        for file in 0..files {
+            let first = *data.get(position)?;
+            let second = if both_have_pawns { *data.get(position + 1)? } else { 0xFF };
+            let order = [[first & 0xF, second & 0xF], [first >> 4, second >> 4]];
+            position += 1 + both_have_pawns as usize;
+
+            for k in 0..piece_count {
+                let byte = *data.get(position)?;
+                for side in 0..sides {
+                    table.parts[side][file].pieces[k] = if side == 1 { byte >> 4 } else { byte & 0xF };
+                }
+                position += 1;
+            }
+            // every part has to encode the pieces of the table, with the leading pawns first
+            let lead_pawn = if white_leads { 1 } else { 9 };
+            for part in table.parts.iter().map(|parts| &parts[file]) {
+                let mut counts = [0u8; 16];
+                for &piece in &part.pieces[..piece_count] { counts[piece as usize] += 1; }
+                if counts != material || part.pieces[..pawn_count[0]].iter().any(|&piece| piece != lead_pawn) { return None; }
+            }
+            for (side, order) in order.into_iter().enumerate().take(sides) { table.set_groups(side, file, order)?; }
+        }
+        position += position & 1;
+
+        for file in 0..files {
+            for side in 0..sides { position = table.set_sizes(&data, side, file, position)?; }
+        }
+        if is_dtz { position = table.set_dtz_map(&data, files, position)?; }
+        for file in 0..files {
+            for part in table.parts.iter_mut() {
+                part[file].sparse_index = position;
+                position += part[file].sparse_index_size * 6;
+            }
+        }
+        for file in 0..files {
+            for part in table.parts.iter_mut() {
+                part[file].block_length = position;
+                position += part[file].block_length_size * 2;
+            }
+        }
+        for file in 0..files {
+            // single value parts have no data, and small tables made only of them end before the alignment
+            for part in table.parts.iter_mut().filter(|part| part[file].block_count > 0) {
+                position = (position + 0x3F) & !0x3F;
+                part[file].data = position;
+                position = position.checked_add(part[file].block_count.checked_mul(part[file].block_size)?)?;
+            }
+        }
+        if position > data.len() { return None; }
+
+        table.data = data;
+        Some(table)
+    }
+
+    /**
+        splits the pieces into the groups encoded together: pieces of the same kind and color, except for the
+        leading group, which is the leading pawns, or three pieces, or the two kings if no other piece is unique
+    */
+    fn set_groups(&mut self, side: usize, file: usize, order: [u8; 2]) -> Option<()> {
+        let (has_pawns, has_unique_pieces, pawn_count, piece_count) = (self.has_pawns, self.has_unique_pieces, self.pawn_count, self.piece_count);
+        let part = &mut self.parts[side][file];
+
+        let mut first_len: i32 = if has_pawns { 0 } else if has_unique_pieces { 3 } else { 2 };
+        let mut n = 0;
+        part.group_len[0] = 1;
+        for i in 1..piece_count {
+            first_len -= 1;
+            if first_len > 0 || part.pieces[i] == part.pieces[i - 1] {
+                part.group_len[n] += 1;
+            } else {
+                n += 1;
+                part.group_len[n] = 1;
+            }
+        }
+        n += 1;
+        part.group_len[n] = 0;
+        if part.group_len[0] >= MAX_PIECES { return None; }
+
+        // the groups aren't encoded in their order: the file says when the leading group and the pawns of the
+        // other side come, and the other pieces fill the remaining places
+        let both_have_pawns = has_pawns && pawn_count[1] > 0;
+        let mut next = if both_have_pawns { 2 } else { 1 };
+        let mut free_squares = 64 - part.group_len[0] - if both_have_pawns { part.group_len[1] } else { 0 };
+        let mut index = 1;
+        let mut k = 0;
+        while next < n || k == order[0] as usize || k == order[1] as usize {
+            if k == order[0] as usize {
+                part.group_index[0] = index;
+                index *= if has_pawns {
+                    MAPS.lead_pawns_size[part.group_len[0]][file]
+                } else if has_unique_pieces {
+                    31332
+                } else {
+                    462
+                };
+            } else if k == order[1] as usize {
+                part.group_index[1] = index;
+                index *= MAPS.binomial[part.group_len[1]][48 - part.group_len[0]];
+            } else {
+                part.group_index[next] = index;
+                index *= MAPS.binomial[part.group_len[next]][free_squares];
+                free_squares -= part.group_len[next];
+                next += 1;
+            }
+            k += 1;
+        }
+        part.group_index[n] = index;
+        Some(())
+    }
+
+    /** reads the sizes and the Huffman code of a part, returning where the next one starts */
+    fn set_sizes(&mut self, data: &[u8], side: usize, file: usize, mut position: usize) -> Option<usize> {
+        let part = &mut self.parts[side][file];
+        part.flags = *data.get(position)?;
+        position += 1;
+        if part.flags & SINGLE_VALUE != 0 {
+            part.min_symbol_length = *data.get(position)?;
+            return Some(position + 1);
+        }
+
+        let positions = part.group_index[part.group_len.iter().position(|&len| len == 0)?];
+        part.block_size = 1usize.checked_shl(u32::from(*data.get(position)?))?;
+        part.span = 1u64.checked_shl(u32::from(*data.get(position + 1)?))?;
+        part.sparse_index_size = positions.div_ceil(part.span) as usize;
+        let padding = *data.get(position + 2)? as usize;
+        part.block_count = read_u32(data, position + 3)? as usize;
+        part.block_length_size = part.block_count + padding;
+        let max_symbol_length = *data.get(position + 7)?;
+        part.min_symbol_length = *data.get(position + 8)?;
+        position += 9;
+        part.lowest_symbol = position;
+        let lengths = max_symbol_length.checked_sub(part.min_symbol_length)? as usize + 1;
+
+        // in a canonical Huffman code, longer codes are smaller numbers, so the smallest code of each length tells
+        // the length of the code at the start of the data
+        part.base64 = vec![0; lengths];
+        for i in (0..lengths - 1).rev() {
+            let lowest = read_u16(data, part.lowest_symbol + 2 * i)? as u64;
+            let next_lowest = read_u16(data, part.lowest_symbol + 2 * (i + 1))? as u64;
+            part.base64[i] = part.base64[i + 1].wrapping_add(lowest).wrapping_sub(next_lowest) / 2;
+        }
+        for (i, base) in part.base64.iter_mut().enumerate() {
+            *base = base.checked_shl((64 - i - part.min_symbol_length as usize) as u32).unwrap_or(0);
+        }
+        position += lengths * 2;
+
+        let symbols = read_u16(data, position)? as usize;
+        position += 2;
+        part.btree = position;
+        part.symbol_length = vec![0; symbols];
+        let mut visited = vec![false; symbols];
+        for symbol in 0..symbols {
+            if !visited[symbol] { part.symbol_length[symbol] = set_symbol_length(data, part, symbol, &mut visited)?; }
+        }
+        Some(position + symbols * 3 + (symbols & 1))
+    }
+
+    /** finds where the maps from stored values to DTZ values are, in mapped DTZ tables */
+    fn set_dtz_map(&mut self, data: &[u8], files: usize, mut position: usize) -> Option<usize> {
+        for file in 0..files {
+            let part = &mut self.parts[0][file];
+            if part.flags & MAPPED == 0 { continue; }
+            if part.flags & WIDE != 0 {
+                position += position & 1;
+                for index in part.map_index.iter_mut() {
+                    *index = position + 2;
+                    position += 2 * read_u16(data, position)? as usize + 2;
+                }
+            } else {
+                for index in part.map_index.iter_mut() {
+                    *index = position + 1;
+                    position += *data.get(position)? as usize + 1;
+                }
+            }
+        }
+        Some(position + (position & 1))
+    }
+
+    fn part(&self, side: usize, file: usize) -> &PairsData {
+        &self.parts[side % self.parts.len()][if self.has_pawns { file } else { 0 }]
+    }
+
+    /**
+        the value stored for the position, which has to have the material of the table: the WDL score from the
+        point of view of the player to play, or the number of plies to the next capture or pawn move (wdl tells
+        which side of the result it is). None if the DTZ table only stores positions with the other side to move
+    */
+    pub(super) fn probe(&self, chessboard: &Chessboard, material: Material, wdl: Wdl) -> Option<Option<i32>> {
+        // tables only store the positions where white has the first side of the name, or where white is to
+        // play if both sides have the same pieces, so the others are probed with the colors swapped
+        let black_to_play = chessboard.to_play == Color::Black;
+        let swap = (self.material == self.swapped_material && black_to_play) || material != self.material;
+        let flip_color = if swap { 8 } else { 0 };
+        let flip_squares = if swap { 56 } else { 0 };
+        let side_to_move = (swap ^ black_to_play) as usize;
+
+        let mut squares = [0u8; MAX_PIECES];
+        let mut pieces = [0u8; MAX_PIECES];
+        let mut size = 0;
+        let mut lead_pawns = 0;
+        let mut file = 0;
+        if self.has_pawns {
+            // the pawns of the leading color come first in every part
+            let pawn = self.part(0, 0).pieces[0] ^ flip_color;
+            let color = if pawn & 8 != 0 { Color::Black } else { Color::White };
+            lead_pawns = chessboard.pieces(PieceType::Pawn, color);
+            for square in bitboard::squares(lead_pawns) {
+                *squares.get_mut(size)? = square ^ flip_squares;
+                size += 1;
+            }
+            let leading = (0..size).rev().max_by_key(|&i| MAPS.pawns[squares[i] as usize])?;
+            squares.swap(0, leading);
+            file = ((squares[0] & 7) as usize).min(7 - (squares[0] & 7) as usize);
+        }
+        let lead_pawn_count = size;
+
+        if self.is_dtz {
+            let flags = self.part(0, file).flags;
+            let stored_side = (flags & STM) as usize;
+            // a symmetric table without pawns looks the same from both sides
+            let symmetric = self.material == self.swapped_material && !self.has_pawns;
+            if stored_side != side_to_move && !symmetric { return Some(None); }
+        }
+
+        for square in bitboard::squares(chessboard.occupancy() ^ lead_pawns) {
+            let piece = chessboard.get(square & 7, square >> 3).ok()??;
+            *squares.get_mut(size)? = square ^ flip_squares;
+            pieces[size] = piece_code(piece.piece_type, piece.color) ^ flip_color;
+            size += 1;
+        }
+        if size != self.piece_count { return None; }
+
+        let part = self.part(side_to_move, file);
+        // the pieces go in the order of the part, the one that compresses it best
+        for i in lead_pawn_count..size - 1 {
+            if let Some(j) = (i + 1..size).find(|&j| part.pieces[i] == pieces[j]) {
+                pieces.swap(i, j);
+                squares.swap(i, j);
+            }
+        }
+
+        // the leading piece goes to the a-d files
+        if squares[0] & 7 > 3 {
+            for square in squares[..size].iter_mut() { *square ^= 7; }
+        }
+
+        let mut index;
+        if self.has_pawns {
+            index = MAPS.lead_pawn_index[lead_pawn_count][squares[0] as usize];
+            squares[1..lead_pawn_count].sort_by_key(|&square| MAPS.pawns[square as usize]);
+            for (i, &square) in squares.iter().enumerate().take(lead_pawn_count).skip(1) {
+                index += MAPS.binomial[i][MAPS.pawns[square as usize] as usize];
+            }
+        } else {
+            // without pawns, the leading piece also goes to the first 4 ranks and below the a1-h8 diagonal
+            if squares[0] >> 3 > 3 {
+                for square in squares[..size].iter_mut() { *square ^= 56; }
+            }
+            for i in 0..part.group_len[0] {
+                if off_diagonal(squares[i]) == 0 { continue; }
+                if off_diagonal(squares[i]) > 0 {
+                    for square in squares[i..size].iter_mut() { *square = ((*square >> 3) | (*square << 3)) & 63; }
+                }
+                break;
+            }
+
+            let rank = |square: u8| (square >> 3) as u64;
+            if self.has_unique_pieces {
+                // the second and third pieces can't be on the squares taken by the ones before
+                let adjust1 = (squares[1] > squares[0]) as u64;
+                let adjust2 = (squares[2] > squares[0]) as u64 + (squares[2] > squares[1]) as u64;
+                let [first, second, third] = [squares[0], squares[1], squares[2]];
+                index = if off_diagonal(first) != 0 {
+                    (MAPS.a1d1d4[first as usize] * 63 + (second as u64 - adjust1)) * 62 + third as u64 - adjust2
+                } else if off_diagonal(second) != 0 {
+                    (6 * 63 + rank(first) * 28 + MAPS.b1h1h7[second as usize]) * 62 + third as u64 - adjust2
+                } else if off_diagonal(third) != 0 {
+                    6 * 63 * 62 + 4 * 28 * 62 + rank(first) * 7 * 28 + (rank(second) - adjust1) * 28 + MAPS.b1h1h7[third as usize]
+                } else {
+                    6 * 63 * 62 + 4 * 28 * 62 + 4 * 7 * 28 + rank(first) * 7 * 6 + (rank(second) - adjust1) * 6 + (rank(third) - adjust2)
+                };
+            } else {
+                index = MAPS.kk[MAPS.a1d1d4[squares[0] as usize] as usize][squares[1] as usize];
+            }
+        }
+
+        // every other group is encoded as a combination of squares, leaving out the squares taken before
+        index *= part.group_index[0];
+        let mut group_start = part.group_len[0];
+        let mut remaining_pawns = self.has_pawns && self.pawn_count[1] > 0;
+        let mut next = 1;
+        while part.group_len[next] != 0 {
+            let group = group_start..group_start + part.group_len[next];
+            squares[group.clone()].sort();
+            let mut n = 0;
+            for (i, &square) in squares[group.clone()].iter().enumerate() {
+                let taken = squares[..group_start].iter().filter(|&&taken| square > taken).count();
+                // the order of the pieces comes from the file, so a bad one can make this go below zero
+                n += MAPS.binomial[i + 1][(square as usize).checked_sub(taken + 8 * remaining_pawns as usize)?];
+            }
+            remaining_pawns = false;
+            index += n * part.group_index[next];
+            group_start = group.end;
+            next += 1;
+        }
+
+        let value = decompress(&self.data, part, index)? as i32;
+        Some(Some(if self.is_dtz { self.map_dtz(file, value, wdl)? } else { value - 2 }))
+    }
+
+    /** turns a value of the DTZ table into plies */
+    fn map_dtz(&self, file: usize, mut value: i32, wdl: Wdl) -> Option<i32> {
+        let part = self.part(0, file);
+        if part.flags & MAPPED != 0 {
+            let map = part.map_index[match wdl {
+                Wdl::Win | Wdl::Draw => 0,
+                Wdl::Loss => 1,
+                Wdl::CursedWin => 2,
+                Wdl::BlessedLoss => 3
+            }];
+            value = if part.flags & WIDE != 0 {
+                read_u16(&self.data, map + 2 * value as usize)? as i32
+            } else {
+                *self.data.get(map + value as usize)? as i32
+            };
+        }
+
+        // the table may count moves instead of plies
+        let in_moves = match wdl {
+            Wdl::Win => part.flags & WIN_PLIES == 0,
+            Wdl::Loss => part.flags & LOSS_PLIES == 0,
+            Wdl::CursedWin | Wdl::BlessedLoss => true,
+            Wdl::Draw => false
+        };
+        if in_moves { value *= 2; }
+        Some(value + 1)
+    }
+}
+
+fn left_symbol(data: &[u8], part: &PairsData, symbol: usize) -> Option<usize> {
+    let node = data.get(part.btree + 3 * symbol..part.btree + 3 * symbol + 3)?;
+    Some(((node[1] as usize & 0xF) << 8) | node[0] as usize)
+}
+fn right_symbol(data: &[u8], part: &PairsData, symbol: usize) -> Option<usize> {
+    let node = data.get(part.btree + 3 * symbol..part.btree + 3 * symbol + 3)?;
+    Some(((node[2] as usize) << 4) | (node[1] as usize >> 4))
+}
+
+/** how many values the symbol stands for, minus one, found by expanding its pairs down to single values */
+fn set_symbol_length(data: &[u8], part: &mut PairsData, symbol: usize, visited: &mut [bool]) -> Option<u8> {
+    visited[symbol] = true;
+    let right = right_symbol(data, part, symbol)?;
+    if right == 0xFFF { return Some(0); }
+    let left = left_symbol(data, part, symbol)?;
+    for child in [left, right] {
+        if !*visited.get(child)? { part.symbol_length[child] = set_symbol_length(data, part, child, visited)?; }
+    }
+    Some(part.symbol_length[left].wrapping_add(part.symbol_length[right]).wrapping_add(1))
+}
+
+/** the value stored at the index of the part */
+fn decompress(data: &[u8], part: &PairsData, index: u64) -> Option<u16> {
+    if part.flags & SINGLE_VALUE != 0 { return Some(part.min_symbol_length as u16); }
+
+    // the sparse index tells which block holds the value at the middle of every span, and where in the block
+    let k = (index / part.span) as usize;
+    let mut block = read_u32(data, part.sparse_index + 6 * k)? as usize;
+    let mut offset = read_u16(data, part.sparse_index + 6 * k + 4)? as i64;
+    offset += (index % part.span) as i64 - (part.span / 2) as i64;
+    let block_length = |block: usize| read_u16(data, part.block_length + 2 * block).map(|length| length as i64);
+    while offset < 0 {
+        block = block.checked_sub(1)?;
+        offset += block_length(block)? + 1;
+    }
+    while offset > block_length(block)? {
+        offset -= block_length(block)? + 1;
+        block += 1;
+    }
+
+    // reads the symbols of the block until reaching the one that holds the value
+    let mut position = part.data + block * part.block_size;
+    let mut buffer = read_u64_be(data, position)?;
+    position += 8;
+    let mut buffer_size = 64;
+    let mut symbol;
+    loop {
+        let mut length = 0;
+        while buffer < *part.base64.get(length)? { length += 1; }
+        let shift = 64usize.checked_sub(length + part.min_symbol_length as usize)? as u32;
+        symbol = (buffer - part.base64[length]).checked_shr(shift).unwrap_or(0) as u16 as usize;
+        symbol = (symbol as u16).wrapping_add(read_u16(data, part.lowest_symbol + 2 * length)?) as usize;
+
+        let values = *part.symbol_length.get(symbol)? as i64 + 1;
+        if offset < values { break; }
+        offset -= values;
+        length += part.min_symbol_length as usize;
+        buffer = buffer.checked_shl(length as u32).unwrap_or(0);
+        buffer_size -= length as i32;
+        if buffer_size <= 32 {
+            buffer_size += 32;
+            buffer |= (read_u32_be(data, position)? as u64) << (64 - buffer_size);
+            position += 4;
+        }
+    }
+
+    // then goes down the pairs of the symbol
+    while *part.symbol_length.get(symbol)? != 0 {
+        let left = left_symbol(data, part, symbol)?;
+        let left_values = *part.symbol_length.get(left)? as i64 + 1;
+        if offset < left_values {
+            symbol = left;
+        } else {
+            offset -= left_values;
+            symbol = right_symbol(data, part, symbol)?;
+        }
+    }
+    left_symbol(data, part, symbol).map(|value| value as u16)
+}
+
+#[test]
+fn index_maps() {
+    assert_eq!(MAPS.kk.iter().flatten().max(), Some(&461));
+    assert_eq!(MAPS.b1h1h7.iter().max(), Some(&27));
+    assert_eq!(MAPS.a1d1d4[1], 0);
+    assert_eq!(MAPS.a1d1d4[27], 9);
+    assert_eq!((MAPS.pawns[8], MAPS.pawns[15], MAPS.pawns[16], MAPS.pawns[51], MAPS.pawns[52]), (47, 46, 45, 1, 0));
+    assert_eq!((MAPS.binomial[2][5], MAPS.binomial[3][62], MAPS.binomial[0][10]), (10, 37820, 1));
+    // one leading pawn can stand on 6 ranks of every file, two on any two of the squares the first one allows
+    assert_eq!(MAPS.lead_pawns_size[1], [6; 4]);
+    assert_eq!(MAPS.lead_pawns_size[2][0], (37..=47).step_by(2).sum());
+}
+
+#[test]
+fn decompression() {
+    // 3 symbols: 0 and 1 are the values 7 and 9 with the codes 00 and 01, 2 is the pair (0, 1) with the code 1
+    let mut data = vec![];
+    let lowest_symbol = data.len();
+    data.extend([2, 0, 0, 0]);
+    let btree = data.len();
+    data.extend([7, 0xF0, 0xFF, 9, 0xF0, 0xFF, 0, 0x10, 0]);
+    // the middle of every span of 8 values: the index 4 in the first block, 12 and 20 (past the end) in the second one
+    let sparse_index = data.len();
+    data.extend([0, 0, 0, 0, 4, 0, 1, 0, 0, 0, 1, 0, 1, 0, 0, 0, 9, 0]);
+    let block_length = data.len();
+    data.extend([10, 0, 7, 0]);
+    let blocks = data.len();
+    // 00 00 01 1 1 01 00 1 and 01 1 00 00 1 00
+    data.extend([0b00000111, 0b01001000, 0, 0, 0, 0, 0, 0]);
+    data.extend([0b01100001, 0b00000000, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+
+    let part = PairsData {
+        block_size: 8,
+        span: 8,
+        min_symbol_length: 1,
+        lowest_symbol,
+        base64: vec![1 << 63, 0],
+        symbol_length: vec![0, 0, 1],
+        btree,
+        sparse_index,
+        block_length,
+        data: blocks,
+        ..PairsData::default()
+    };
+    let values: Vec<u16> = (0..19).map(|index| decompress(&data, &part, index).unwrap()).collect();
+    assert_eq!(values, [7, 7, 9, 7, 9, 7, 9, 9, 7, 7, 9, 9, 7, 9, 7, 7, 7, 9, 7]);
+    assert_eq!(decompress(&data, &PairsData { flags: SINGLE_VALUE, min_symbol_length: 3, ..PairsData::default() }, 12345), Some(3));
+}
@@ -0,0 +1,107 @@
+use std::time::Duration;
+
+use crate::chessboard::Move;
+
+// time management (https://www.chessprogramming.org/Time_Management) splits the time left on the clock between the
+// moves still to play. The search gets two limits: it doesn't start a new depth after the soft one, and it is stopped
+// right away at the hard one. When the best move keeps changing from one depth to the next the position is harder
+// than it looks, so the soft limit is pushed back until the search settles, never further than the hard limit
+
+/** time kept aside for the GUI to receive the move, so the engine doesn't lose on time because of lag */
+pub const MOVE_OVERHEAD: Duration = Duration::from_millis(30);
+/** how many moves a game is expected to still last at its start, and at least at any point of it */
+const MAX_MOVES_LEFT: u32 = 50;
+const MIN_MOVES_LEFT: u32 = 20;
+/** the hard limit is that many times the soft one, but never more than a part of the time left */
+const HARD_LIMIT_FACTOR: u32 = 4;
+/** how far the soft limit can be pushed back when the best move is unstable, as a multiple of itself */
+const MAX_EXTENSION: f64 = 3.0;
+
+/** the clock of the player to play */
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Clock {
+    pub time: Duration,
+    pub increment: Duration,
+    /** the moves left until the next time control, None if all the remaining time is for the rest of the game */
+    pub moves_to_go: Option<u32>
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeManager {
+    soft_limit: Duration,
+    hard_limit: Duration,
+    best_move: Option<Move>,
+    /** goes up by one every time the best move changes and halves at every depth */
+    instability: f64
+}
+
+impl TimeManager {
+    /** the limits of a search from the position at that move number with that much time left on the clock */
+    pub fn new(clock: Clock, move_number: u16) -> Self {
+        let usable = clock.time.saturating_sub(MOVE_OVERHEAD);
+        let moves_left = clock.moves_to_go
+            .unwrap_or_else(|| MAX_MOVES_LEFT.saturating_sub(move_number as u32 / 2).max(MIN_MOVES_LEFT))
+            .max(1);
+
+        // the increment comes back after the move, so most of it can be spent right away
+        let soft_limit = usable / moves_left + clock.increment * 3 / 4;
+        let hard_limit = (soft_limit * HARD_LIMIT_FACTOR).min(usable * 3 / 4);
+        TimeManager { soft_limit: soft_limit.min(hard_limit), hard_limit, best_move: None, instability: 0.0 }
+    }
+
+    /** the search shouldn't start a new depth after this long */
+    pub fn soft_limit(&self) -> Duration {
+        self.soft_limit.mul_f64((1.0 + self.instability).min(MAX_EXTENSION)).min(self.hard_limit)
+    }
+    /** the search has to stop after this long, even in the middle of a depth */
+    pub fn hard_limit(&self) -> Duration {
+        self.hard_limit
+    }
+
+    /** tells the time manager the best move found by the depth the search just completed */
+    pub fn depth_completed(&mut self, best_move: Option<Move>) {
+        self.instability /= 2.0;
+        if self.best_move.is_some() && best_move != self.best_move { self.instability += 1.0; }
+        self.best_move = best_move;
+    }
+}
+
+#[test]
+fn time_management() {
+    let seconds = Duration::from_secs;
+    let clock = Clock { time: seconds(60), increment: Duration::ZERO, moves_to_go: None };
+
+    // the same time lasts for fewer moves later in the game
+    let opening = TimeManager::new(clock, 1);
+    let endgame = TimeManager::new(clock, 80);
+    assert_eq!(opening.soft_limit(), (seconds(60) - MOVE_OVERHEAD) / 50);
+    assert_eq!(endgame.soft_limit(), (seconds(60) - MOVE_OVERHEAD) / 20);
+    assert_eq!(opening.hard_limit(), opening.soft_limit() * 4);
+
+    let with_increment = TimeManager::new(Clock { increment: seconds(2), ..clock }, 1);
+    assert_eq!(with_increment.soft_limit(), opening.soft_limit() + Duration::from_millis(1500));
+    let time_control = TimeManager::new(Clock { moves_to_go: Some(10), ..clock }, 1);
+    assert_eq!(time_control.soft_limit(), (seconds(60) - MOVE_OVERHEAD) / 10);
+
+    // the last move before the time control or an almost empty clock never use up all the time
+    for clock in [Clock { moves_to_go: Some(1), ..clock }, Clock { time: Duration::from_millis(100), increment: seconds(1), moves_to_go: None }] {
+        let time_manager = TimeManager::new(clock, 30);
+        assert!(time_manager.soft_limit() <= time_manager.hard_limit());
+        assert!(time_manager.hard_limit() < clock.time - MOVE_OVERHEAD);
+    }
+    assert_eq!(TimeManager::new(Clock::default(), 1).hard_limit(), Duration::ZERO);
+
+    // the soft limit grows while the best move changes, and goes back once it stays the same
+    let r#move = |string: &str| Move::from_string(string, None);
+    let mut time_manager = opening.clone();
+    time_manager.depth_completed(r#move("e2e4"));
+    time_manager.depth_completed(r#move("e2e4"));
+    assert_eq!(time_manager.soft_limit(), opening.soft_limit());
+    time_manager.depth_completed(r#move("d2d4"));
+    assert_eq!(time_manager.soft_limit(), opening.soft_limit() * 2);
+    time_manager.depth_completed(r#move("c2c4"));
+    assert!(time_manager.soft_limit() > opening.soft_limit() * 2);
+    assert!(time_manager.soft_limit() <= opening.hard_limit());
+    for _ in 0..20 { time_manager.depth_completed(r#move("c2c4")); }
+    assert!(time_manager.soft_limit() < opening.soft_limit() + Duration::from_millis(1));
+}
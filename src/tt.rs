@@ -0,0 +1,155 @@
+use crate::chessboard::Move;
+use crate::search::{is_mate_score, MATE_SCORE};
+
+// a transposition table (https://www.chessprogramming.org/Transposition_Table) remembers what the search found
+// about every position by its Zobrist hash, so positions reached again through other move orders aren't searched
+// again. It is split in buckets of two entries: the first keeps the deepest search of the position it holds, and
+// the second always takes the latest one
+
+pub const DEFAULT_MEGABYTES: usize = 16;
+
+/** how the stored score relates to the real score of the position */
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Bound {
+    /** the score is exact */
+    Exact,
+    /** the search failed high, the real score is at least the stored one */
+    Lower,
+    /** the search failed low, the real score is at most the stored one */
+    Upper
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Entry {
+    /** the full hash, to tell apart the positions that land in the same bucket */
+    pub key: u64,
+    pub best_move: Option<Move>,
+    /** mates are counted from this position, not from the root of the search that stored it */
+    pub score: i32,
+    pub depth: u8,
+    pub bound: Bound,
+    /** the search that stored the entry, entries of older searches are the first ones to go */
+    pub age: u8
+}
+
+type Bucket = [Option<Entry>; 2];
+
+/** mate scores count plies from the root, but the table needs them to count from the stored position */
+pub fn score_to_table(score: i32, ply: usize) -> i32 {
+    if !is_mate_score(score) { return score; }
+    if score > 0 { score + ply as i32 } else { score - ply as i32 }
+}
+/** the opposite of score_to_table, for an entry found at the ply */
+pub fn score_from_table(score: i32, ply: usize) -> i32 {
+    if !is_mate_score(score) { return score; }
+    if score > 0 { (score - ply as i32).min(MATE_SCORE) } else { (score + ply as i32).max(-MATE_SCORE) }
+}
+
+pub struct TranspositionTable {
+    buckets: Vec<Bucket>,
+    age: u8
+}
+
+impl TranspositionTable {
+    /** a table taking about that many megabytes of memory, with room for at least one bucket */
+    pub fn new(megabytes: usize) -> Self {
+        let bucket_count = (megabytes * 1024 * 1024 / std::mem::size_of::<Bucket>()).max(1);
+        TranspositionTable { buckets: vec![[None; 2]; bucket_count], age: 0 }
+    }
+
+    /** the number of entries the table can hold */
+    pub fn capacity(&self) -> usize {
+        self.buckets.len() * 2
+    }
+
+    /** forgets everything, like at the start of a new game */
+    pub fn clear(&mut self) {
+        self.buckets.fill([None; 2]);
+        self.age = 0;
+    }
+
+    /** has to be called before every search, so entries of previous ones can be told apart and replaced first */
+    pub fn new_search(&mut self) {
+        self.age = self.age.wrapping_add(1);
+    }
+
+    fn bucket_index(&self, key: u64) -> usize {
+        // maps the key to 0..len evenly without needing a power of two size
+        ((key as u128 * self.buckets.len() as u128) >> 64) as usize
+    }
+
+    pub fn probe(&self, key: u64) -> Option<Entry> {
+        self.buckets[self.bucket_index(key)].iter().flatten().find(|entry| entry.key == key).copied()
+    }
+
+    /** stores what the search found about the position, the score being the one found at that ply of the search */
+    pub fn store(&mut self, key: u64, depth: u8, bound: Bound, score: i32, best_move: Option<Move>, ply: usize) {
+        let age = self.age;
+        let index = self.bucket_index(key);
+        let bucket = &mut self.buckets[index];
+
+        let mut entry = Entry { key, best_move, score: score_to_table(score, ply), depth, bound, age };
+        // a search that didn't find a best move shouldn't erase the one found before
+        if let Some(old) = bucket.iter().flatten().find(|old| old.key == key) {
+            if entry.best_move.is_none() { entry.best_move = old.best_move; }
+        }
+
+        let slot = match bucket {
+            [Some(old), _] if old.key == key => 0,
+            [_, Some(old)] if old.key == key => 1,
+            [None, _] => 0,
+            [Some(old), _] if old.age != age || depth >= old.depth => 0,
+            _ => 1
+        };
+        // when the depth-preferred slot is taken over, its previous entry is still good for the other slot
+        if slot == 0 && bucket[0].is_some_and(|old| old.key != key) { bucket[1] = bucket[0]; }
+        bucket[slot] = Some(entry);
+    }
+
+    /** how full the table is in permille, sampling the first thousand entries, the way UCI wants it for hashfull */
+    pub fn hashfull(&self) -> usize {
+        let sample = &self.buckets[..self.buckets.len().min(500)];
+        let used = sample.iter().flatten().flatten().filter(|entry| entry.age == self.age).count();
+        used * 1000 / (sample.len() * 2)
+    }
+}
+
+#[test]
+fn transposition_table() {
+    let r#move = Move::from_string("e2e4", None);
+    let mut table = TranspositionTable::new(1);
+    assert_eq!(table.capacity(), 1024 * 1024 / std::mem::size_of::<Bucket>() * 2);
+    assert_eq!(TranspositionTable::new(0).capacity(), 2);
+
+    table.new_search();
+    table.store(42, 3, Bound::Exact, 25, r#move, 5);
+    assert_eq!(table.probe(42), Some(Entry { key: 42, best_move: r#move, score: 25, depth: 3, bound: Bound::Exact, age: 1 }));
+    assert_eq!(table.probe(43), None);
+    // a search without a best move keeps the old one
+    table.store(42, 4, Bound::Upper, 10, None, 5);
+    assert_eq!(table.probe(42).unwrap().best_move, r#move);
+    assert_eq!(table.probe(42).unwrap().depth, 4);
+    assert!(table.hashfull() > 0);
+    table.clear();
+    assert_eq!((table.probe(42), table.hashfull()), (None, 0));
+
+    // a mate in 3 plies found 2 plies deep is a mate in 1 ply from the stored position, and a mate in 5 from another ply
+    table.store(7, 1, Bound::Exact, MATE_SCORE - 3, None, 2);
+    assert_eq!(table.probe(7).unwrap().score, MATE_SCORE - 1);
+    assert_eq!(score_from_table(table.probe(7).unwrap().score, 4), MATE_SCORE - 5);
+    assert_eq!(score_from_table(score_to_table(-MATE_SCORE + 6, 3), 1), -MATE_SCORE + 4);
+    assert_eq!(score_from_table(score_to_table(150, 3), 9), 150);
+
+    // in a table of one bucket, the deepest entry stays in the first slot and the others take turns in the second one
+    let mut table = TranspositionTable::new(0);
+    table.store(1, 8, Bound::Exact, 0, None, 0);
+    table.store(2, 2, Bound::Exact, 0, None, 0);
+    table.store(3, 2, Bound::Exact, 0, None, 0);
+    assert!(table.probe(1).is_some() && table.probe(2).is_none() && table.probe(3).is_some());
+    table.store(4, 9, Bound::Lower, 0, None, 0);
+    assert!(table.probe(4).is_some() && table.probe(1).is_some() && table.probe(3).is_none());
+    // entries from older searches give way even to shallower ones
+    table.new_search();
+    table.store(5, 1, Bound::Upper, 0, None, 0);
+    assert!(table.probe(5).is_some() && table.probe(4).is_some() && table.probe(1).is_none());
+}
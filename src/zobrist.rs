@@ -0,0 +1,54 @@
+// random keys for Zobrist hashing (https://www.chessprogramming.org/Zobrist_Hashing), generated at compile
+// time with splitmix64 so they are the same on every run
+
+const fn splitmix64(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9E3779B97F4A7C15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    (state, z ^ (z >> 31))
+}
+
+pub(crate) struct Keys {
+    /** indexed by piece code and then by square (x + 8 * y) */
+    pub pieces: [[u64; 64]; 12],
+    /** toggled when black is to play */
+    pub black_to_play: u64,
+    /** in the same order as Chessboard::castling */
+    pub castling: [u64; 4],
+    /** indexed by the file of the en passant target */
+    pub en_passant: [u64; 8]
+}
+
+const fn generate_keys() -> Keys {
+    let mut keys = Keys { pieces: [[0; 64]; 12], black_to_play: 0, castling: [0; 4], en_passant: [0; 8] };
+    let mut state = 0x1D8E4E27C47D124F;
+    let mut key;
+
+    let mut piece = 0;
+    while piece < 12 {
+        let mut square = 0;
+        while square < 64 {
+            (state, key) = splitmix64(state);
+            keys.pieces[piece][square] = key;
+            square += 1;
+        }
+        piece += 1;
+    }
+    (state, keys.black_to_play) = splitmix64(state);
+    let mut i = 0;
+    while i < 4 {
+        (state, key) = splitmix64(state);
+        keys.castling[i] = key;
+        i += 1;
+    }
+    i = 0;
+    while i < 8 {
+        (state, key) = splitmix64(state);
+        keys.en_passant[i] = key;
+        i += 1;
+    }
+    keys
+}
+
+pub(crate) static KEYS: Keys = generate_keys();